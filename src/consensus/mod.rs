@@ -1,4 +1,7 @@
 
+pub mod rlp;
+
+use crate::execution::types::{ Address, H256 };
 
 // 4.1. World State.
 //
@@ -39,6 +42,7 @@
 //         contained in the state database under their corresponding hashes for later retrieval. This hash is
 //         formally denoted σ[a]c, and thus the code may be
 //         denoted as b, given that KEC(b) = σ[a]c.
+#[allow(dead_code, non_snake_case)] // Not used by the interpreter yet, and named as in the yellow paper
 struct AccountState {
     nonce: u128,
     balance: u128,
@@ -119,6 +123,7 @@ struct AccountState {
 //
 //     data: An unlimited size byte array specifying the
 //         input data of the message call, formally Td.
+#[allow(dead_code, non_snake_case)]
 struct Transaction {
     r#type: u128,
     nonce: u128,
@@ -128,20 +133,24 @@ struct Transaction {
     value: u128,
 }
 
+#[allow(dead_code, non_snake_case)]
 struct EIP2930Trasaction { // + Transaction
     accessList: Vec<u128>,
     chainId: u8,
     yParity: u128,
 }
 
+#[allow(dead_code)]
 struct LegacyTransaction { // + Transaction
     w: u128,
 }
 
+#[allow(dead_code)]
 struct ContractCreationTransaction { // + EIP2930Transaction or LegacyTransaction
-    init: String,
+    init: Vec<u8>,
 }
 
+#[allow(dead_code)]
 struct MessageCallTransaction { // + EIP2930Transaction or LegacyTransaction
     data: String,
 }
@@ -199,6 +208,11 @@ struct MessageCallTransaction { // + EIP2930Transaction or LegacyTransaction
 //    nonce: A 64-bit value which, combined with the mixhash, proves that a sufficient amount of computation
 //         has been carried out on this block; formally
 //         Hn.
+//    baseFeePerGas: A scalar value equal to the amount of wei that is burned for each unit of gas consumed;
+//         formally Hf. Introduced by EIP-1559.
+//
+// Since the merge (EIP-4399) mixHash instead carries the beacon chain's randomness, prevRandao, and
+// difficulty is zero.
 //
 // The other two components in the block are simply a list
 // of ommer block headers (of the same format as above),
@@ -206,22 +220,24 @@ struct MessageCallTransaction { // + EIP2930Transaction or LegacyTransaction
 // refer to a block B:
 //
 //     (21) B ≡ (BH, BT, BU)
-struct Block {
-    parentHash: String,
-    ommersHash: String,
-    beneficiary: u128,
-    stateRoot: String,
-    transactionRoot: String,
-    receiptsRoot: String,
-    logsBloom: u128,
-    difficulty: u128,
-    number: u128,
-    gasLimit: u128,
-    gasUsed: u128,
-    timestamp: u128,
-    extraData: String,
-    mixHash: String,
-    nonce: u64,
+#[allow(non_snake_case)] // Field names follow the yellow paper
+pub struct Block {
+    pub parentHash: String,
+    pub ommersHash: String,
+    pub beneficiary: Address,
+    pub stateRoot: String,
+    pub transactionRoot: String,
+    pub receiptsRoot: String,
+    pub logsBloom: u128,
+    pub difficulty: u128,
+    pub number: u128,
+    pub gasLimit: u128,
+    pub gasUsed: u128,
+    pub timestamp: u128,
+    pub extraData: String,
+    pub mixHash: H256,
+    pub nonce: u64,
+    pub baseFeePerGas: u128,
 }
 
 // 4.3.1. Transaction Receipt.
@@ -0,0 +1,73 @@
+// Appendix B. Recursive Length Prefix.
+//
+// A serialisation method for encoding arbitrarily structured binary data (byte arrays). Items are either
+// byte arrays or sequences (lists) of items, each prefixed by its length.
+
+// Single bytes below 0x80 are their own encoding, otherwise the length is prefixed
+pub fn encode_bytes(bytes: &[u8]) -> Vec<u8> {
+    if bytes.len() == 1 && bytes[0] < 0x80 {
+        return bytes.to_vec();
+    }
+    let mut encoded = encode_length(bytes.len(), 0x80);
+    encoded.extend_from_slice(bytes);
+    encoded
+}
+
+// Scalars are encoded as big endian byte arrays with no leading zeroes, so zero is the empty array
+pub fn encode_u64(value: u64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let leading_zeroes = bytes.iter().take_while(|&&byte| byte == 0).count();
+    encode_bytes(&bytes[leading_zeroes..])
+}
+
+// Items must already be encoded
+pub fn encode_list(items: &[Vec<u8>]) -> Vec<u8> {
+    let payload: Vec<u8> = items.concat();
+    let mut encoded = encode_length(payload.len(), 0xc0);
+    encoded.extend(payload);
+    encoded
+}
+
+// Payloads of up to 55 bytes add their length to the offset, longer ones give the length of their length
+fn encode_length(length: usize, offset: u8) -> Vec<u8> {
+    if length <= 55 {
+        return vec![offset + length as u8];
+    }
+    let bytes = (length as u64).to_be_bytes();
+    let leading_zeroes = bytes.iter().take_while(|&&byte| byte == 0).count();
+    let mut encoded = vec![offset + 55 + (bytes.len() - leading_zeroes) as u8];
+    encoded.extend_from_slice(&bytes[leading_zeroes..]);
+    encoded
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes() {
+        assert_eq!(vec![0x83, b'd', b'o', b'g'], encode_bytes(b"dog"));
+        assert_eq!(vec![0x80], encode_bytes(b""));
+        assert_eq!(vec![0x0f], encode_bytes(&[0x0f]));
+        assert_eq!(vec![0x81, 0x80], encode_bytes(&[0x80]));
+
+        let long = [b'a'; 56];
+        assert_eq!(vec![0xb8, 56], encode_bytes(&long)[..2]);
+        assert_eq!(58, encode_bytes(&long).len());
+    }
+
+    #[test]
+    fn scalars() {
+        assert_eq!(vec![0x80], encode_u64(0));
+        assert_eq!(vec![0x0f], encode_u64(15));
+        assert_eq!(vec![0x82, 0x04, 0x00], encode_u64(1024));
+    }
+
+    #[test]
+    fn lists() {
+        assert_eq!(vec![0xc0], encode_list(&[]));
+        let cat_dog = encode_list(&[encode_bytes(b"cat"), encode_bytes(b"dog")]);
+        assert_eq!(vec![0xc8, 0x83, b'c', b'a', b't', 0x83, b'd', b'o', b'g'], cat_dog);
+    }
+}
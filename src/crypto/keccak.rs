@@ -0,0 +1,111 @@
+// Keccak-256 as used throughout Ethereum. This is the original Keccak submission, which differs from the
+// standardised SHA3-256 only in its padding (0x01 rather than 0x06).
+
+const RATE: usize = 136; // (1600 - 2 * 256) / 8 bytes absorbed per permutation
+
+const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001, 0x0000000000008082, 0x800000000000808a, 0x8000000080008000,
+    0x000000000000808b, 0x0000000080000001, 0x8000000080008081, 0x8000000000008009,
+    0x000000000000008a, 0x0000000000000088, 0x0000000080008009, 0x000000008000000a,
+    0x000000008000808b, 0x800000000000008b, 0x8000000000008089, 0x8000000000008003,
+    0x8000000000008002, 0x8000000000000080, 0x000000000000800a, 0x800000008000000a,
+    0x8000000080008081, 0x8000000000008080, 0x0000000080000001, 0x8000000080008008,
+];
+
+// Rho rotation applied to each lane as it is moved along the pi permutation
+const RHO: [u32; 24] = [1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44];
+const PI: [usize; 24] = [10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4, 15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1];
+
+// Keccak-f[1600], state lanes are indexed x + 5y
+fn keccak_f(state: &mut [u64; 25]) {
+    for round_constant in ROUND_CONSTANTS {
+        // Theta
+        let mut columns = [0u64; 5];
+        for (x, column) in columns.iter_mut().enumerate() {
+            *column = state[x] ^ state[x + 5] ^ state[x + 10] ^ state[x + 15] ^ state[x + 20];
+        }
+        for x in 0..5 {
+            let d = columns[(x + 4) % 5] ^ columns[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                state[x + 5 * y] ^= d;
+            }
+        }
+
+        // Rho and pi
+        let mut last = state[1];
+        for (&lane, &rotation) in PI.iter().zip(RHO.iter()) {
+            let next = state[lane];
+            state[lane] = last.rotate_left(rotation);
+            last = next;
+        }
+
+        // Chi
+        for y in 0..5 {
+            let mut row = [0u64; 5];
+            row.copy_from_slice(&state[5 * y..5 * y + 5]);
+            for x in 0..5 {
+                state[x + 5 * y] = row[x] ^ (!row[(x + 1) % 5] & row[(x + 2) % 5]);
+            }
+        }
+
+        // Iota
+        state[0] ^= round_constant;
+    }
+}
+
+fn absorb(state: &mut [u64; 25], block: &[u8]) {
+    for (lane, bytes) in state.iter_mut().zip(block.chunks_exact(8)) {
+        let mut le_bytes = [0u8; 8];
+        le_bytes.copy_from_slice(bytes);
+        *lane ^= u64::from_le_bytes(le_bytes);
+    }
+    keccak_f(state);
+}
+
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut state = [0u64; 25];
+
+    let mut blocks = data.chunks_exact(RATE);
+    for block in &mut blocks {
+        absorb(&mut state, block);
+    }
+
+    // The remainder always leaves room for at least one byte of padding
+    let remainder = blocks.remainder();
+    let mut last_block = [0u8; RATE];
+    last_block[..remainder.len()].copy_from_slice(remainder);
+    last_block[remainder.len()] ^= 0x01;
+    last_block[RATE - 1] ^= 0x80;
+    absorb(&mut state, &last_block);
+
+    let mut hash = [0u8; 32];
+    for (bytes, lane) in hash.chunks_exact_mut(8).zip(state.iter()) {
+        bytes.copy_from_slice(&lane.to_le_bytes());
+    }
+    hash
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::program_context::encode_hex;
+
+    #[test]
+    fn empty() {
+        assert_eq!("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470", encode_hex(&keccak256(b"")));
+    }
+
+    #[test]
+    fn short() {
+        assert_eq!("4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45", encode_hex(&keccak256(b"abc")));
+    }
+
+    #[test]
+    fn block_boundaries() {
+        // One byte short of the rate, exactly the rate and spanning two blocks
+        assert_eq!("34367dc248bbd832f4e3e69dfaac2f92638bd0bbd18f2912ba4ef454919cf446", encode_hex(&keccak256(&[0x61; 135])));
+        assert_eq!("a6c4d403279fe3e0af03729caada8374b5ca54d8065329a3ebcaeb4b60aa386e", encode_hex(&keccak256(&[0x61; 136])));
+        assert_eq!("96ea54061def936c4be90b518992fdc6f12f535068a256229aca54267b4d084d", encode_hex(&keccak256(&[0x61; 200])));
+    }
+}
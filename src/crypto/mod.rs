@@ -0,0 +1,9 @@
+pub mod keccak;
+pub mod modexp;
+pub mod ripemd160;
+pub mod sha256;
+
+pub use keccak::keccak256;
+pub use modexp::modexp;
+pub use ripemd160::ripemd160;
+pub use sha256::sha256;
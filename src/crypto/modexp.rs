@@ -0,0 +1,191 @@
+// Arbitrary precision modular exponentiation, used by the 0x05 precompile (EIP-198). Numbers are held as
+// little endian 32-bit limbs with no leading zero limbs, so zero is empty.
+
+type Limbs = Vec<u32>;
+
+fn from_be_bytes(bytes: &[u8]) -> Limbs {
+    let mut limbs: Limbs = bytes.rchunks(4).map(|chunk| chunk.iter().fold(0u32, |limb, &byte| (limb << 8) | byte as u32)).collect();
+    normalise(&mut limbs);
+    limbs
+}
+
+// Big endian, left padded with zeroes to the given size
+fn to_be_bytes(limbs: &[u32], size: usize) -> Vec<u8> {
+    let bytes: Vec<u8> = limbs.iter().rev().flat_map(|limb| limb.to_be_bytes()).collect();
+    let significant = &bytes[bytes.iter().take_while(|&&byte| byte == 0).count()..];
+    let mut padded = vec![0u8; size.saturating_sub(significant.len())];
+    padded.extend_from_slice(&significant[significant.len().saturating_sub(size)..]);
+    padded
+}
+
+fn normalise(limbs: &mut Limbs) {
+    while limbs.last() == Some(&0) {
+        limbs.pop();
+    }
+}
+
+fn mul(a: &[u32], b: &[u32]) -> Limbs {
+    let mut product = vec![0u32; a.len() + b.len()];
+    for (i, &x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, &y) in b.iter().enumerate() {
+            let t = x as u64 * y as u64 + product[i + j] as u64 + carry;
+            product[i + j] = t as u32;
+            carry = t >> 32;
+        }
+        product[i + b.len()] = carry as u32;
+    }
+    normalise(&mut product);
+    product
+}
+
+// Knuth's algorithm D (TAOCP 4.3.1), keeping only the remainder. The modulus must be non-zero.
+fn rem(a: &[u32], m: &[u32]) -> Limbs {
+    if a.len() < m.len() {
+        return a.to_vec();
+    }
+    if m.len() == 1 {
+        let remainder = a.iter().rev().fold(0u64, |remainder, &limb| ((remainder << 32) | limb as u64) % m[0] as u64);
+        let mut remainder = vec![remainder as u32];
+        normalise(&mut remainder);
+        return remainder;
+    }
+
+    // Shift both so the modulus' top limb has its high bit set, which keeps the quotient estimates close
+    let shift = m[m.len() - 1].leading_zeros();
+    let v = shl(m, shift);
+    let mut u = shl(a, shift);
+    u.resize(a.len() + 1, 0);
+    let n = v.len();
+
+    for j in (0..u.len() - n).rev() {
+        let numerator = ((u[j + n] as u64) << 32) | u[j + n - 1] as u64;
+        let mut q = numerator / v[n - 1] as u64;
+        let mut r = numerator % v[n - 1] as u64;
+        while q > u32::MAX as u64 || q * v[n - 2] as u64 > ((r << 32) | u[j + n - 2] as u64) {
+            q -= 1;
+            r += v[n - 1] as u64;
+            if r > u32::MAX as u64 {
+                break;
+            }
+        }
+
+        // Subtract q * v from the current window of u, adding v back if q was still one too large
+        let mut borrow = 0i64;
+        let mut carry = 0u64;
+        for i in 0..n {
+            let product = q * v[i] as u64 + carry;
+            carry = product >> 32;
+            let t = u[i + j] as i64 - borrow - (product & 0xffffffff) as i64;
+            u[i + j] = t as u32;
+            borrow = if t < 0 { 1 } else { 0 };
+        }
+        let t = u[j + n] as i64 - borrow - carry as i64;
+        u[j + n] = t as u32;
+        if t < 0 {
+            let mut carry = 0u64;
+            for i in 0..n {
+                let t = u[i + j] as u64 + v[i] as u64 + carry;
+                u[i + j] = t as u32;
+                carry = t >> 32;
+            }
+            u[j + n] = u[j + n].wrapping_add(carry as u32);
+        }
+    }
+
+    u.truncate(n);
+    let mut remainder = shr(&u, shift);
+    normalise(&mut remainder);
+    remainder
+}
+
+fn shl(limbs: &[u32], shift: u32) -> Limbs {
+    if shift == 0 {
+        return limbs.to_vec();
+    }
+    let mut shifted = Vec::with_capacity(limbs.len() + 1);
+    let mut carry = 0u32;
+    for &limb in limbs {
+        shifted.push((limb << shift) | carry);
+        carry = limb >> (32 - shift);
+    }
+    if carry != 0 {
+        shifted.push(carry);
+    }
+    shifted
+}
+
+fn shr(limbs: &[u32], shift: u32) -> Limbs {
+    if shift == 0 {
+        return limbs.to_vec();
+    }
+    let mut shifted = vec![0u32; limbs.len()];
+    for i in 0..limbs.len() {
+        let high = limbs.get(i + 1).map(|&limb| limb << (32 - shift)).unwrap_or(0);
+        shifted[i] = (limbs[i] >> shift) | high;
+    }
+    shifted
+}
+
+// base^exponent % modulus, all big endian. The result is the same size as the modulus, and zero if the
+// modulus is zero.
+pub fn modexp(base: &[u8], exponent: &[u8], modulus: &[u8]) -> Vec<u8> {
+    let m = from_be_bytes(modulus);
+    if m.is_empty() {
+        return vec![0u8; modulus.len()];
+    }
+
+    // Left to right square and multiply
+    let base = rem(&from_be_bytes(base), &m);
+    let mut result = rem(&[1], &m);
+    for byte in exponent {
+        for bit in (0..8).rev() {
+            result = rem(&mul(&result, &result), &m);
+            if byte & (1 << bit) != 0 {
+                result = rem(&mul(&result, &base), &m);
+            }
+        }
+    }
+    to_be_bytes(&result, modulus.len())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u128_modexp(base: u128, exponent: u128, modulus: u128) -> u128 {
+        let mut result = 1 % modulus;
+        for _ in 0..exponent {
+            result = result * base % modulus;
+        }
+        result
+    }
+
+    #[test]
+    fn small() {
+        // Moduli spanning one and two limbs, checked against native arithmetic
+        for &(base, exponent, modulus) in &[(3u128, 5u128, 7u128), (2, 10, 1000), (0xffffffff, 3, 0x1_0000_0001), (12345, 17, 0xfffffffffffff), (7, 0, 1)] {
+            let expected = u128_modexp(base, exponent, modulus).to_be_bytes();
+            let actual = modexp(&base.to_be_bytes(), &exponent.to_be_bytes(), &modulus.to_be_bytes());
+            assert_eq!(expected.to_vec(), actual);
+        }
+    }
+
+    #[test]
+    fn fermat() {
+        // 3^(p - 1) = 1 mod p for the secp256k1 field prime, from EIP-198
+        let p = [vec![0xff; 27], vec![0xfe, 0xff, 0xff, 0xfc, 0x2f]].concat();
+        let p_minus_one = [vec![0xff; 27], vec![0xfe, 0xff, 0xff, 0xfc, 0x2e]].concat();
+        let mut one = vec![0u8; 32];
+        one[31] = 1;
+        assert_eq!(one, modexp(&[0x03], &p_minus_one, &p));
+        assert_eq!(vec![0u8; 32], modexp(&[], &p_minus_one, &p));
+    }
+
+    #[test]
+    fn zero_modulus() {
+        assert_eq!(vec![0u8; 2], modexp(&[0x02], &[0x02], &[0x00, 0x00]));
+        assert!(modexp(&[0x02], &[0x02], &[]).is_empty());
+    }
+}
@@ -0,0 +1,139 @@
+// RIPEMD-160, used by the 0x03 precompile. Two parallel lines of five rounds each process every block,
+// the results being combined at the end.
+
+const BLOCK_SIZE: usize = 64;
+
+const INITIAL_STATE: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+// Constants for each of the five rounds, left and right lines
+const K_LEFT: [u32; 5] = [0x00000000, 0x5a827999, 0x6ed9eba1, 0x8f1bbcdc, 0xa953fd4e];
+const K_RIGHT: [u32; 5] = [0x50a28be6, 0x5c4dd124, 0x6d703ef3, 0x7a6d76e9, 0x00000000];
+
+// Message word selected at each step
+const R_LEFT: [usize; 80] = [
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+    7, 4, 13, 1, 10, 6, 15, 3, 12, 0, 9, 5, 2, 14, 11, 8,
+    3, 10, 14, 4, 9, 15, 8, 1, 2, 7, 0, 6, 13, 11, 5, 12,
+    1, 9, 11, 10, 0, 8, 12, 4, 13, 3, 7, 15, 14, 5, 6, 2,
+    4, 0, 5, 9, 7, 12, 2, 10, 14, 1, 3, 8, 11, 6, 15, 13,
+];
+const R_RIGHT: [usize; 80] = [
+    5, 14, 7, 0, 9, 2, 11, 4, 13, 6, 15, 8, 1, 10, 3, 12,
+    6, 11, 3, 7, 0, 13, 5, 10, 14, 15, 8, 12, 4, 9, 1, 2,
+    15, 5, 1, 3, 7, 14, 6, 9, 11, 8, 12, 2, 10, 0, 4, 13,
+    8, 6, 4, 1, 3, 11, 15, 0, 5, 12, 2, 13, 9, 7, 10, 14,
+    12, 15, 10, 4, 1, 5, 8, 7, 6, 2, 13, 14, 0, 3, 9, 11,
+];
+
+// Left rotation applied at each step
+const S_LEFT: [u32; 80] = [
+    11, 14, 15, 12, 5, 8, 7, 9, 11, 13, 14, 15, 6, 7, 9, 8,
+    7, 6, 8, 13, 11, 9, 7, 15, 7, 12, 15, 9, 11, 7, 13, 12,
+    11, 13, 6, 7, 14, 9, 13, 15, 14, 8, 13, 6, 5, 12, 7, 5,
+    11, 12, 14, 15, 14, 15, 9, 8, 9, 14, 5, 6, 8, 6, 5, 12,
+    9, 15, 5, 11, 6, 8, 13, 12, 5, 12, 13, 14, 11, 8, 5, 6,
+];
+const S_RIGHT: [u32; 80] = [
+    8, 9, 9, 11, 13, 15, 15, 5, 7, 7, 8, 11, 14, 14, 12, 6,
+    9, 13, 15, 7, 12, 8, 9, 11, 7, 7, 12, 7, 6, 15, 13, 11,
+    9, 7, 15, 11, 8, 6, 6, 14, 12, 13, 5, 14, 13, 13, 7, 5,
+    15, 5, 8, 11, 14, 14, 6, 14, 6, 9, 12, 9, 12, 5, 15, 8,
+    8, 5, 12, 9, 12, 5, 14, 6, 8, 13, 6, 5, 15, 13, 11, 11,
+];
+
+// Boolean function for each round, the right line uses them in reverse order
+fn f(round: usize, x: u32, y: u32, z: u32) -> u32 {
+    match round {
+        0 => x ^ y ^ z,
+        1 => (x & y) | (!x & z),
+        2 => (x | !y) ^ z,
+        3 => (x & z) | (y & !z),
+        _ => x ^ (y | !z),
+    }
+}
+
+fn compress(state: &mut [u32; 5], block: &[u8]) {
+    let mut words = [0u32; 16];
+    for (word, bytes) in words.iter_mut().zip(block.chunks_exact(4)) {
+        *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+
+    let [mut al, mut bl, mut cl, mut dl, mut el] = *state;
+    let [mut ar, mut br, mut cr, mut dr, mut er] = *state;
+    for step in 0..80 {
+        let round = step / 16;
+
+        let t = al
+            .wrapping_add(f(round, bl, cl, dl))
+            .wrapping_add(words[R_LEFT[step]])
+            .wrapping_add(K_LEFT[round])
+            .rotate_left(S_LEFT[step])
+            .wrapping_add(el);
+        al = el;
+        el = dl;
+        dl = cl.rotate_left(10);
+        cl = bl;
+        bl = t;
+
+        let t = ar
+            .wrapping_add(f(4 - round, br, cr, dr))
+            .wrapping_add(words[R_RIGHT[step]])
+            .wrapping_add(K_RIGHT[round])
+            .rotate_left(S_RIGHT[step])
+            .wrapping_add(er);
+        ar = er;
+        er = dr;
+        dr = cr.rotate_left(10);
+        cr = br;
+        br = t;
+    }
+
+    let t = state[1].wrapping_add(cl).wrapping_add(dr);
+    state[1] = state[2].wrapping_add(dl).wrapping_add(er);
+    state[2] = state[3].wrapping_add(el).wrapping_add(ar);
+    state[3] = state[4].wrapping_add(al).wrapping_add(br);
+    state[4] = state[0].wrapping_add(bl).wrapping_add(cr);
+    state[0] = t;
+}
+
+pub fn ripemd160(data: &[u8]) -> [u8; 20] {
+    let mut state = INITIAL_STATE;
+
+    let mut blocks = data.chunks_exact(BLOCK_SIZE);
+    for block in &mut blocks {
+        compress(&mut state, block);
+    }
+
+    // Padded as in SHA-256, but the length is little endian
+    let remainder = blocks.remainder();
+    let mut padding = [0u8; 2 * BLOCK_SIZE];
+    padding[..remainder.len()].copy_from_slice(remainder);
+    padding[remainder.len()] = 0x80;
+    let padded_size = if remainder.len() < BLOCK_SIZE - 8 { BLOCK_SIZE } else { 2 * BLOCK_SIZE };
+    padding[padded_size - 8..padded_size].copy_from_slice(&((data.len() as u64) * 8).to_le_bytes());
+    for block in padding[..padded_size].chunks_exact(BLOCK_SIZE) {
+        compress(&mut state, block);
+    }
+
+    let mut hash = [0u8; 20];
+    for (bytes, word) in hash.chunks_exact_mut(4).zip(state.iter()) {
+        bytes.copy_from_slice(&word.to_le_bytes());
+    }
+    hash
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::program_context::encode_hex;
+
+    #[test]
+    fn vectors() {
+        assert_eq!("9c1185a5c5e9fc54612808977ee8f548b2258d31", encode_hex(&ripemd160(b"")));
+        assert_eq!("8eb208f7e05d987a9b044a8e98c6b087f15a0bfc", encode_hex(&ripemd160(b"abc")));
+        assert_eq!("5d0689ef49d2fae572b881b123a85ffa21595f36", encode_hex(&ripemd160(b"message digest")));
+        // 56 bytes, so the length spills into a second padding block
+        assert_eq!("12a053384a9c0c88e405a06c27dcf49ada62eb2b", encode_hex(&ripemd160(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")));
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use super::instructions::{ Instructions, OpCode };
+use super::program_context::encode_hex;
+
+// Turns mnemonic source back into bytecode, for writing test fixtures by hand:
+//
+//     PUSH1 0x03          ; explicitly sized push
+//     @loop:              ; a label, assembled as a JUMPDEST
+//     PUSH 1 SWAP1 SUB    // PUSH picks the smallest size that fits
+//     DUP1 @loop JUMPI    ; a bare label or literal is pushed the same way
+//
+// Mnemonics are case insensitive, literals are hex with a 0x prefix or decimal, and comments start with ;
+// or // and run to the end of the line.
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AssembleError {
+    UnknownMnemonic { mnemonic: String, line: usize },
+    InvalidLiteral { literal: String, line: usize },
+    LiteralTooLarge { literal: String, size: usize, line: usize },
+    MissingOperand { mnemonic: String, line: usize },
+    DuplicateLabel { label: String, line: usize },
+    UndefinedLabel { label: String, line: usize },
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssembleError::UnknownMnemonic { mnemonic, line } => write!(f, "line {}: unknown mnemonic {}", line, mnemonic),
+            AssembleError::InvalidLiteral { literal, line } => write!(f, "line {}: invalid literal {}", line, literal),
+            AssembleError::LiteralTooLarge { literal, size, line } => write!(f, "line {}: {} doesn't fit in {} bytes", line, literal, size),
+            AssembleError::MissingOperand { mnemonic, line } => write!(f, "line {}: {} needs a value to push", line, mnemonic),
+            AssembleError::DuplicateLabel { label, line } => write!(f, "line {}: label @{} is already defined", line, label),
+            AssembleError::UndefinedLabel { label, line } => write!(f, "line {}: label @{} isn't defined", line, label),
+        }
+    }
+}
+
+enum Operand {
+    Literal(Vec<u8>), // Big endian, without leading zeroes
+    Label(String),
+}
+
+enum Item {
+    Opcode(u8),
+    Push { size: Option<usize>, operand: Operand, line: usize }, // No size means the smallest that fits
+    Label(String),
+}
+
+pub fn assemble(source: &str) -> Result<Vec<u8>, AssembleError> {
+    let items = parse(source)?;
+
+    // Label offsets depend on the size of the pushes before them, which can depend on label offsets in turn.
+    // Start every label push at its smallest and grow them until nothing moves. Sizes only ever grow, so
+    // this settles.
+    let mut label_sizes: Vec<usize> = vec![0; items.len()];
+    let labels = loop {
+        let mut labels = HashMap::new();
+        let mut offset = 0;
+        for (item, &label_size) in items.iter().zip(&label_sizes) {
+            if let Item::Label(label) = item {
+                labels.insert(label.as_str(), offset);
+            }
+            offset += match item {
+                Item::Opcode(_) | Item::Label(_) => 1,
+                Item::Push { size: Some(size), .. } => 1 + size,
+                Item::Push { size: None, operand: Operand::Literal(value), .. } => 1 + value.len(),
+                Item::Push { size: None, operand: Operand::Label(_), .. } => 1 + label_size,
+            };
+        }
+
+        let mut changed = false;
+        for (item, label_size) in items.iter().zip(label_sizes.iter_mut()) {
+            if let Item::Push { size: None, operand: Operand::Label(label), .. } = item {
+                let size = labels.get(label.as_str()).map(|&offset| significant_bytes(offset).len()).unwrap_or(0);
+                if size > *label_size {
+                    *label_size = size;
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            break labels;
+        }
+    };
+
+    let mut code = Vec::new();
+    for (item, &label_size) in items.iter().zip(&label_sizes) {
+        match item {
+            Item::Opcode(opcode) => code.push(*opcode),
+            Item::Label(_) => code.push(OpCode::JumpDest as u8),
+            Item::Push { size, operand, line } => {
+                let value = match operand {
+                    Operand::Literal(value) => value.clone(),
+                    Operand::Label(label) => match labels.get(label.as_str()) {
+                        Some(&offset) => significant_bytes(offset),
+                        None => return Err(AssembleError::UndefinedLabel { label: label.clone(), line: *line }),
+                    },
+                };
+                let size = size.unwrap_or(match operand {
+                    Operand::Literal(_) => value.len(),
+                    Operand::Label(_) => label_size,
+                });
+                if value.len() > size {
+                    let literal = match operand {
+                        Operand::Literal(_) => format!("0x{}", encode_hex(&value)),
+                        Operand::Label(label) => format!("@{}", label),
+                    };
+                    return Err(AssembleError::LiteralTooLarge { literal, size, line: *line });
+                }
+                code.push(OpCode::Push0 as u8 + size as u8);
+                code.extend(std::iter::repeat_n(0, size - value.len()));
+                code.extend_from_slice(&value);
+            },
+        }
+    }
+    Ok(code)
+}
+
+fn parse(source: &str) -> Result<Vec<Item>, AssembleError> {
+    let mut items = Vec::new();
+    let mut defined = HashMap::new();
+    for (index, text) in source.lines().enumerate() {
+        let line = index + 1;
+        let text = text.split(';').next().unwrap_or_default();
+        let text = text.split("//").next().unwrap_or_default();
+
+        let mut tokens = text.split_whitespace();
+        while let Some(token) = tokens.next() {
+            if let Some(label) = token.strip_prefix('@').and_then(|label| label.strip_suffix(':')) {
+                if defined.insert(label.to_string(), line).is_some() {
+                    return Err(AssembleError::DuplicateLabel { label: label.to_string(), line });
+                }
+                items.push(Item::Label(label.to_string()));
+                continue;
+            }
+
+            // A bare label or literal is an automatically sized push
+            if token.starts_with('@') || token.starts_with(|c: char| c.is_ascii_digit()) {
+                items.push(Item::Push { size: None, operand: parse_operand(token, line)?, line });
+                continue;
+            }
+
+            let mnemonic = token.to_ascii_uppercase();
+            if mnemonic == "PUSH" {
+                let operand = tokens.next().ok_or_else(|| AssembleError::MissingOperand { mnemonic: token.to_string(), line })?;
+                items.push(Item::Push { size: None, operand: parse_operand(operand, line)?, line });
+                continue;
+            }
+            let instruction = Instructions.values()
+                .find(|instruction| instruction.mnemonic == mnemonic)
+                .ok_or_else(|| AssembleError::UnknownMnemonic { mnemonic: token.to_string(), line })?;
+            if instruction.rom_items_used == 0 {
+                items.push(Item::Opcode(instruction.value));
+                continue;
+            }
+            let operand = tokens.next().ok_or_else(|| AssembleError::MissingOperand { mnemonic: token.to_string(), line })?;
+            items.push(Item::Push { size: Some(instruction.rom_items_used as usize), operand: parse_operand(operand, line)?, line });
+        }
+    }
+    Ok(items)
+}
+
+fn parse_operand(token: &str, line: usize) -> Result<Operand, AssembleError> {
+    if let Some(label) = token.strip_prefix('@') {
+        return Ok(Operand::Label(label.to_string()));
+    }
+    let invalid = || AssembleError::InvalidLiteral { literal: token.to_string(), line };
+    let value = match token.strip_prefix("0x").or_else(|| token.strip_prefix("0X")) {
+        Some(digits) => parse_digits(digits, 16),
+        None => parse_digits(token, 10),
+    }.ok_or_else(invalid)?;
+    if value.len() > 32 {
+        return Err(AssembleError::LiteralTooLarge { literal: token.to_string(), size: 32, line });
+    }
+    Ok(Operand::Literal(value))
+}
+
+// Big endian bytes without leading zeroes, so zero is empty
+fn parse_digits(digits: &str, radix: u32) -> Option<Vec<u8>> {
+    if digits.is_empty() {
+        return None;
+    }
+    let mut value: Vec<u8> = Vec::new();
+    for c in digits.chars() {
+        let mut carry = c.to_digit(radix)?;
+        for byte in value.iter_mut().rev() {
+            let t = *byte as u32 * radix + carry;
+            *byte = t as u8;
+            carry = t >> 8;
+        }
+        if carry != 0 {
+            value.insert(0, carry as u8);
+        }
+    }
+    Some(value)
+}
+
+fn significant_bytes(value: usize) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    bytes[bytes.iter().take_while(|&&byte| byte == 0).count()..].to_vec()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mnemonics() {
+        let source = "
+            PUSH1 0x03 push2 258  ; sizes given
+            PUSH 0x0100 PUSH 0    // sized automatically, zero as PUSH0
+            ADD 7 stop
+        ";
+        assert_eq!("6003610102610100 5f01 6007 00".replace(' ', ""), encode_hex(&assemble(source).unwrap()));
+        assert_eq!(vec![0x7f, 0xff], assemble(&format!("PUSH 0x{}", "ff".repeat(32))).unwrap()[..2]);
+    }
+
+    #[test]
+    fn labels() {
+        // Backward and forward references
+        let source = "
+            @loop:
+            PUSH 1 SWAP1 SUB
+            DUP1 @loop JUMPI
+            @end JUMP
+            @end:
+        ";
+        assert_eq!("5b 600190 03 80 5f 57 600b 56 5b".replace(' ', ""), encode_hex(&assemble(source).unwrap()));
+
+        // A label past 0xff needs a PUSH2, which moves it further still
+        let source = format!("@end JUMP {} @end:", "STOP ".repeat(253));
+        let code = assemble(&source).unwrap();
+        assert_eq!(vec![0x61, 0x01, 0x01, 0x56], code[..4]);
+        assert_eq!(0x5b, code[0x101]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(Err(AssembleError::UnknownMnemonic { mnemonic: String::from("PUSHY"), line: 2 }), assemble("STOP\nPUSHY"));
+        assert_eq!(Err(AssembleError::InvalidLiteral { literal: String::from("0xzz"), line: 1 }), assemble("PUSH 0xzz"));
+        assert_eq!(Err(AssembleError::MissingOperand { mnemonic: String::from("PUSH1"), line: 1 }), assemble("PUSH1"));
+        assert_eq!(Err(AssembleError::UndefinedLabel { label: String::from("nowhere"), line: 1 }), assemble("@nowhere JUMP"));
+        assert_eq!(Err(AssembleError::DuplicateLabel { label: String::from("a"), line: 2 }), assemble("@a:\n@a:"));
+        assert!(matches!(assemble("PUSH1 0x0100"), Err(AssembleError::LiteralTooLarge { size: 1, .. })));
+    }
+}
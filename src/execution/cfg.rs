@@ -0,0 +1,214 @@
+use std::collections::HashSet;
+
+use super::disassembler::{ is_terminator, push_value, DisassembledInstruction, Disassembly };
+use super::instructions::OpCode;
+
+// Where a jump goes. Only a PUSH immediately before the jump can be followed, anything computed is dynamic.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Target {
+    Static(usize),
+    Invalid, // A constant that isn't a JUMPDEST, so the jump always fails
+    Dynamic,
+}
+
+// How control leaves a block
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Exit {
+    Halt, // STOP, RETURN, REVERT, INVALID, SELFDESTRUCT, or running off the end of the code
+    FallThrough(usize), // Into the JUMPDEST that starts the next block
+    Jump(Target),
+    JumpI { target: Target, fall_through: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub start: usize,
+    pub instructions: Vec<DisassembledInstruction>,
+    pub exit: Exit,
+}
+
+impl BasicBlock {
+    // Offsets of the blocks control can pass to
+    pub fn successors(&self) -> Vec<usize> {
+        match self.exit {
+            Exit::Halt | Exit::Jump(Target::Invalid) | Exit::Jump(Target::Dynamic) => Vec::new(),
+            Exit::FallThrough(next) | Exit::Jump(Target::Static(next)) => vec![next],
+            Exit::JumpI { target: Target::Static(target), fall_through } => vec![target, fall_through],
+            Exit::JumpI { fall_through, .. } => vec![fall_through],
+        }
+    }
+}
+
+// Basic blocks in code order. A block starts at the beginning of the code, a JUMPDEST or after a JUMPI, and
+// ends with the first jump or terminator. Data regions are left out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlFlowGraph {
+    pub blocks: Vec<BasicBlock>,
+}
+
+impl ControlFlowGraph {
+    pub fn new(disassembly: &Disassembly) -> ControlFlowGraph {
+        let instructions: Vec<&DisassembledInstruction> = disassembly.instructions().collect();
+        let jump_destinations: HashSet<usize> = instructions.iter()
+            .filter(|instruction| instruction.opcode == OpCode::JumpDest as u8)
+            .map(|instruction| instruction.offset)
+            .collect();
+
+        let mut blocks: Vec<BasicBlock> = Vec::new();
+        let mut current: Vec<DisassembledInstruction> = Vec::new();
+        for (i, &instruction) in instructions.iter().enumerate() {
+            if instruction.opcode == OpCode::JumpDest as u8 && !current.is_empty() {
+                let start = current[0].offset;
+                blocks.push(BasicBlock { start, instructions: current, exit: Exit::FallThrough(instruction.offset) });
+                current = Vec::new();
+            }
+            current.push(instruction.clone());
+
+            let ends_block = is_terminator(instruction.opcode) || instruction.opcode == OpCode::JumpI as u8;
+            let last = i + 1 == instructions.len();
+            if !ends_block && !last {
+                continue;
+            }
+
+            let target = || match current.len().checked_sub(2).map(|i| &current[i]) {
+                Some(push) if push.mnemonic.is_some_and(|mnemonic| mnemonic.starts_with("PUSH")) => {
+                    match push_value(&push.immediate) {
+                        Some(offset) if jump_destinations.contains(&offset) => Target::Static(offset),
+                        _ => Target::Invalid,
+                    }
+                },
+                _ => Target::Dynamic,
+            };
+            let exit = match instruction.opcode {
+                opcode if opcode == OpCode::Jump as u8 => Exit::Jump(target()),
+                opcode if opcode == OpCode::JumpI as u8 => Exit::JumpI { target: target(), fall_through: instruction.next_offset() },
+                _ => Exit::Halt,
+            };
+            let start = current[0].offset;
+            blocks.push(BasicBlock { start, instructions: current, exit });
+            current = Vec::new();
+        }
+
+        // A JUMPI at the very end of the code falls through into nothing, which halts, so only its jump remains
+        let starts: HashSet<usize> = blocks.iter().map(|block| block.start).collect();
+        for block in &mut blocks {
+            if let Exit::JumpI { target, fall_through } = block.exit {
+                if !starts.contains(&fall_through) {
+                    block.exit = match target {
+                        Target::Static(_) | Target::Dynamic => Exit::Jump(target),
+                        Target::Invalid => Exit::Halt,
+                    };
+                }
+            }
+        }
+        ControlFlowGraph { blocks }
+    }
+
+    pub fn block(&self, start: usize) -> Option<&BasicBlock> {
+        self.blocks.binary_search_by_key(&start, |block| block.start).ok().map(|i| &self.blocks[i])
+    }
+
+    // Graphviz DOT, one box per block. Dynamic jumps lead to a shared "unresolved" node, conditional jumps
+    // are labelled true and false.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph cfg {\n    node [shape=box, fontname=\"monospace\"];\n");
+        let mut unresolved = false;
+        for block in &self.blocks {
+            let label: String = block.instructions.iter()
+                .map(|instruction| format!("{:04x}: {}\\l", instruction.offset, instruction))
+                .collect();
+            dot.push_str(&format!("    block_{:04x} [label=\"{}\"];\n", block.start, label));
+
+            let edge = |to: String, attributes: &str| format!("    block_{:04x} -> {}{};\n", block.start, to, attributes);
+            let target = |target: Target| match target {
+                Target::Static(offset) => Some(format!("block_{:04x}", offset)),
+                Target::Dynamic => Some(String::from("unresolved")),
+                Target::Invalid => None,
+            };
+            match block.exit {
+                Exit::Halt => {},
+                Exit::FallThrough(next) => dot.push_str(&edge(format!("block_{:04x}", next), "")),
+                Exit::Jump(jump) => {
+                    if let Some(to) = target(jump) {
+                        dot.push_str(&edge(to, ""));
+                    }
+                },
+                Exit::JumpI { target: jump, fall_through } => {
+                    if let Some(to) = target(jump) {
+                        dot.push_str(&edge(to, " [label=\"true\"]"));
+                    }
+                    dot.push_str(&edge(format!("block_{:04x}", fall_through), " [label=\"false\"]"));
+                },
+            }
+            unresolved |= matches!(block.exit, Exit::Jump(Target::Dynamic) | Exit::JumpI { target: Target::Dynamic, .. });
+        }
+        if unresolved {
+            dot.push_str("    unresolved [label=\"unresolved jump\", shape=ellipse, style=dashed];\n");
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::assembler::assemble;
+
+    fn cfg(source: &str) -> ControlFlowGraph {
+        ControlFlowGraph::new(&Disassembly::new(&assemble(source).unwrap()))
+    }
+
+    #[test]
+    fn blocks() {
+        let graph = cfg("
+            PUSH1 0x0a @loop:       ; 0x00, 0x02
+            PUSH1 1 SWAP1 SUB
+            DUP1 @loop JUMPI        ; 0x07
+            @end JUMP STOP          ; 0x0b, then unreachable
+            @end:                   ; 0x0f
+            CALLDATALOAD JUMP
+        ");
+        let starts: Vec<usize> = graph.blocks.iter().map(|block| block.start).collect();
+        assert_eq!(vec![0x00, 0x02, 0x0b, 0x0f], starts);
+        assert_eq!(Exit::FallThrough(0x02), graph.blocks[0].exit);
+        assert_eq!(Exit::JumpI { target: Target::Static(0x02), fall_through: 0x0b }, graph.blocks[1].exit);
+        assert_eq!(Exit::Jump(Target::Static(0x0f)), graph.blocks[2].exit);
+        assert_eq!(Exit::Jump(Target::Dynamic), graph.block(0x0f).unwrap().exit);
+        assert_eq!(vec![0x02, 0x0b], graph.blocks[1].successors());
+    }
+
+    #[test]
+    fn exits() {
+        // Jumping to something that isn't a JUMPDEST
+        assert_eq!(Exit::Jump(Target::Invalid), cfg("PUSH1 0x01 JUMP").blocks[0].exit);
+        assert_eq!(Exit::Halt, cfg("PUSH1 0x01 RETURN").blocks[0].exit);
+        // Running off the end
+        assert_eq!(Exit::Halt, cfg("PUSH1 0x01").blocks[0].exit);
+        assert_eq!(Exit::Halt, cfg("CALLVALUE PUSH1 0x00 JUMPI").blocks[0].exit);
+        // Still an unresolved jump when it might be taken
+        let graph = cfg("CALLVALUE CALLDATALOAD JUMPI");
+        assert_eq!(Exit::Jump(Target::Dynamic), graph.blocks[0].exit);
+        assert!(graph.to_dot().contains("block_0000 -> unresolved;"));
+        assert!(cfg("").blocks.is_empty());
+    }
+
+    #[test]
+    fn dot() {
+        let graph = cfg("CALLVALUE @end JUMPI CALLDATALOAD JUMP @end: STOP");
+        let expected = concat!(
+            "digraph cfg {\n",
+            "    node [shape=box, fontname=\"monospace\"];\n",
+            "    block_0000 [label=\"0000: CALLVALUE\\l0001: PUSH1 0x06\\l0003: JUMPI\\l\"];\n",
+            "    block_0000 -> block_0006 [label=\"true\"];\n",
+            "    block_0000 -> block_0004 [label=\"false\"];\n",
+            "    block_0004 [label=\"0004: CALLDATALOAD\\l0005: JUMP\\l\"];\n",
+            "    block_0004 -> unresolved;\n",
+            "    block_0006 [label=\"0006: JUMPDEST\\l0007: STOP\\l\"];\n",
+            "    unresolved [label=\"unresolved jump\", shape=ellipse, style=dashed];\n",
+            "}\n",
+        );
+        assert_eq!(expected, graph.to_dot());
+    }
+}
@@ -0,0 +1,321 @@
+use std::collections::{ HashMap, HashSet };
+
+use super::cfg::ControlFlowGraph;
+use super::disassembler::{ disassemble, push_value, DataKind, Disassembly, Segment };
+use super::instructions::OpCode;
+use super::program_context::encode_hex;
+
+// Beyond this many removed and added lines the two codes have little in common, and the rest is shown as
+// replaced wholesale rather than spending quadratic time and memory aligning it.
+const MAX_EDITS: usize = 2000;
+
+// An instruction or unreachable data region, as printed and as compared
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub offset: usize,
+    pub text: String,
+    pub block: usize, // Start of the basic block it's in
+    key: String,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Change {
+    Same { old: usize, new: usize }, // Indices into the old and new lines
+    Removed(usize),
+    Added(usize),
+}
+
+// An instruction aligned diff of two codes. Solidity's metadata is left out, and when lines are compared
+// things that change without the code changing are ignored:
+//
+// - Jump destinations pushed right before a JUMP or JUMPI, as any change moves the code after it. They're
+//   compared as which JUMPDEST they are, counting from the start of the code, rather than by offset.
+// - PUSH32 values if asked to, as that's how immutables are embedded. Changes to other 32 byte constants
+//   are hidden too, so it's off by default.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diff {
+    pub old: Vec<Line>,
+    pub new: Vec<Line>,
+    pub changes: Vec<Change>,
+}
+
+impl Diff {
+    pub fn new(old: &[u8], new: &[u8], ignore_immutables: bool) -> Diff {
+        let (old, new) = (lines(old, ignore_immutables), lines(new, ignore_immutables));
+        let changes = align(&old, &new);
+        Diff { old, new, changes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.iter().all(|change| matches!(change, Change::Same { .. }))
+    }
+
+    // Like a unified diff, with old and new offsets on each line. Each basic block with a change in it is
+    // shown whole, under a header giving where it starts in the old and new code.
+    pub fn to_text(&self) -> String {
+        let mut removed_from = HashSet::new();
+        let mut added_to = HashSet::new();
+        for change in &self.changes {
+            match *change {
+                Change::Removed(old) => { removed_from.insert(self.old[old].block); },
+                Change::Added(new) => { added_to.insert(self.new[new].block); },
+                Change::Same { .. } => {},
+            }
+        }
+
+        let mut text = String::new();
+        let mut header: Option<(usize, usize)> = None; // Blocks of the hunk being shown
+        for (i, change) in self.changes.iter().enumerate() {
+            let (old, new) = match *change {
+                Change::Same { old, new } => (Some(&self.old[old]), Some(&self.new[new])),
+                Change::Removed(old) => (Some(&self.old[old]), None),
+                Change::Added(new) => (None, Some(&self.new[new])),
+            };
+            let shown = old.is_some_and(|line| removed_from.contains(&line.block)) || new.is_some_and(|line| added_to.contains(&line.block));
+            if !shown {
+                header = None;
+                continue;
+            }
+
+            let in_hunk = header.is_some_and(|(old_block, new_block)| {
+                old.is_none_or(|line| line.block == old_block) && new.is_none_or(|line| line.block == new_block)
+            });
+            if !in_hunk {
+                let (old_block, new_block) = self.blocks_at(i);
+                text.push_str(&format!("@@ {:04x} -> {:04x} @@\n", old_block, new_block));
+                header = Some((old_block, new_block));
+            }
+
+            let line = match (old, new) {
+                (Some(old), Some(new)) => format!(" {:04x} {:04x} {}", old.offset, new.offset, new.text),
+                (Some(old), None) => format!("-{:04x}      {}", old.offset, old.text),
+                (None, Some(new)) => format!("+     {:04x} {}", new.offset, new.text),
+                (None, None) => unreachable!(),
+            };
+            text.push_str(&line);
+            text.push('\n');
+        }
+        text
+    }
+
+    // Blocks that hold the change at an index. For a line on only one side, the other side's block is that of
+    // the next line there, or the last one if there's none after it.
+    fn blocks_at(&self, index: usize) -> (usize, usize) {
+        let mut old_block = None;
+        let mut new_block = None;
+        for change in self.changes[index..].iter().chain(self.changes[..index].iter().rev()) {
+            match *change {
+                Change::Same { old, new } => {
+                    old_block = old_block.or(Some(self.old[old].block));
+                    new_block = new_block.or(Some(self.new[new].block));
+                },
+                Change::Removed(old) => old_block = old_block.or(Some(self.old[old].block)),
+                Change::Added(new) => new_block = new_block.or(Some(self.new[new].block)),
+            }
+            if old_block.is_some() && new_block.is_some() {
+                break;
+            }
+        }
+        (old_block.unwrap_or(0), new_block.unwrap_or(0))
+    }
+}
+
+fn lines(code: &[u8], ignore_immutables: bool) -> Vec<Line> {
+    let disassembly = Disassembly::new(code);
+    let graph = ControlFlowGraph::new(&disassembly);
+    let starts: Vec<usize> = graph.blocks.iter().map(|block| block.start).collect();
+    let block = |offset: usize| starts[..starts.partition_point(|&start| start <= offset)].last().copied().unwrap_or(0);
+
+    // Every valid JUMPDEST, numbered in code order, whether or not it's reachable
+    let code_end = disassembly.metadata.as_ref().map(|metadata| metadata.offset).unwrap_or(code.len());
+    let jump_destinations: HashMap<usize, usize> = disassemble(&code[..code_end]).iter()
+        .filter(|instruction| instruction.opcode == OpCode::JumpDest as u8)
+        .enumerate()
+        .map(|(i, instruction)| (instruction.offset, i))
+        .collect();
+
+    let mut lines: Vec<Line> = Vec::new();
+    let segments = &disassembly.segments;
+    for (i, segment) in segments.iter().enumerate() {
+        let line = match segment {
+            Segment::Code(instruction) => {
+                let jumps = match segments.get(i + 1) {
+                    Some(Segment::Code(next)) => next.opcode == OpCode::Jump as u8 || next.opcode == OpCode::JumpI as u8,
+                    _ => false,
+                };
+                let mnemonic = instruction.mnemonic.unwrap_or_default();
+                let destination = push_value(&instruction.immediate).and_then(|value| jump_destinations.get(&value));
+                let key = match destination {
+                    Some(ordinal) if mnemonic.starts_with("PUSH") && jumps => format!("PUSH jump destination {}", ordinal),
+                    _ if ignore_immutables && instruction.opcode == OpCode::Push32 as u8 => String::from("PUSH32 immutable"),
+                    _ => instruction.to_string(),
+                };
+                Line { offset: instruction.offset, text: instruction.to_string(), block: block(instruction.offset), key }
+            },
+            Segment::Data(region) if region.kind == DataKind::Unreachable => {
+                let text = format!("DATA 0x{}", encode_hex(&region.bytes));
+                Line { offset: region.offset, text: text.clone(), block: block(region.offset), key: text }
+            },
+            Segment::Data(_) => continue,
+        };
+        lines.push(line);
+    }
+    lines
+}
+
+// Common lines at either end are matched directly, Myers' algorithm aligns what's between them
+fn align(old: &[Line], new: &[Line]) -> Vec<Change> {
+    let prefix = old.iter().zip(new).take_while(|(old, new)| old.key == new.key).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(old, new)| old.key == new.key).count();
+    let old_middle: Vec<&str> = old[prefix..old.len() - suffix].iter().map(|line| line.key.as_str()).collect();
+    let new_middle: Vec<&str> = new[prefix..new.len() - suffix].iter().map(|line| line.key.as_str()).collect();
+
+    let mut changes: Vec<Change> = (0..prefix).map(|i| Change::Same { old: i, new: i }).collect();
+    let middle = myers(&old_middle, &new_middle).unwrap_or_else(|| {
+        (0..old_middle.len()).map(Change::Removed).chain((0..new_middle.len()).map(Change::Added)).collect()
+    });
+    changes.extend(middle.into_iter().map(|change| match change {
+        Change::Same { old, new } => Change::Same { old: old + prefix, new: new + prefix },
+        Change::Removed(old) => Change::Removed(old + prefix),
+        Change::Added(new) => Change::Added(new + prefix),
+    }));
+    changes.extend((0..suffix).map(|i| Change::Same { old: old.len() - suffix + i, new: new.len() - suffix + i }));
+    changes
+}
+
+// E. Myers, An O(ND) Difference Algorithm and Its Variations. None if it takes more than MAX_EDITS edits.
+fn myers(a: &[&str], b: &[&str]) -> Option<Vec<Change>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (n + m) as usize;
+    let offset = max as isize + 1;
+    let mut v = vec![0isize; 2 * max + 3]; // Furthest x reached on each diagonal k = x - y
+    let mut trace: Vec<Vec<isize>> = Vec::new(); // v over -d..=d before each step d
+
+    for d in 0..=max as isize {
+        if d as usize > MAX_EDITS {
+            return None;
+        }
+        trace.push((-d..=d).map(|k| v[(k + offset) as usize]).collect());
+        for k in (-d..=d).step_by(2) {
+            let down = k == -d || (k != d && v[(k - 1 + offset) as usize] < v[(k + 1 + offset) as usize]);
+            let mut x = if down { v[(k + 1 + offset) as usize] } else { v[(k - 1 + offset) as usize] + 1 };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[(k + offset) as usize] = x;
+            if x >= n && y >= m {
+                return Some(backtrack(&trace, n, m));
+            }
+        }
+    }
+    None
+}
+
+fn backtrack(trace: &[Vec<isize>], n: isize, m: isize) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let at = |k: isize| v[(k + d) as usize];
+        let k = x - y;
+        let (previous_x, previous_y) = if d == 0 {
+            (0, 0)
+        } else {
+            let down = k == -d || (k != d && at(k - 1) < at(k + 1));
+            let previous_k = if down { k + 1 } else { k - 1 };
+            (at(previous_k), at(previous_k) - previous_k)
+        };
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+            changes.push(Change::Same { old: x as usize, new: y as usize });
+        }
+        if d > 0 {
+            if x == previous_x {
+                changes.push(Change::Added(previous_y as usize));
+            } else {
+                changes.push(Change::Removed(previous_x as usize));
+            }
+        }
+        (x, y) = (previous_x, previous_y);
+    }
+    changes.reverse();
+    changes
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::assembler::assemble;
+
+    #[test]
+    fn changes() {
+        let old = assemble("PUSH1 1 PUSH1 2 ADD PUSH1 3 MUL POP STOP").unwrap();
+        let new = assemble("PUSH1 1 PUSH1 2 SUB PUSH1 3 MUL CALLER POP STOP").unwrap();
+        let diff = Diff::new(&old, &new, false);
+        assert_eq!(vec![
+            Change::Same { old: 0, new: 0 },
+            Change::Same { old: 1, new: 1 },
+            Change::Removed(2),
+            Change::Added(2),
+            Change::Same { old: 3, new: 3 },
+            Change::Same { old: 4, new: 4 },
+            Change::Added(5),
+            Change::Same { old: 5, new: 6 },
+            Change::Same { old: 6, new: 7 },
+        ], diff.changes);
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn normalised() {
+        // Different metadata, immutable and jump destinations moved by a longer push
+        let metadata = |patch: u8| vec![0xa1, 0x64, b's', b'o', b'l', b'c', 0x43, 0x00, 0x08, patch, 0x00, 0x0a];
+        let old = [assemble("PUSH32 0x01 POP PUSH1 1 @end JUMP @end: STOP").unwrap(), metadata(0x18)].concat();
+        let new = [assemble("PUSH32 0x02 POP PUSH2 0x0100 @end JUMP @end: STOP").unwrap(), metadata(0x18)].concat();
+        assert!(!Diff::new(&old, &new, true).is_empty()); // The PUSH1 changed to a PUSH2 all the same
+
+        let new = [assemble("PUSH32 0x02 POP PUSH1 1 CALLER POP @end JUMP @end: STOP").unwrap(), metadata(0x19)].concat();
+        assert!(!Diff::new(&old, &new, true).is_empty());
+        let new = [assemble("PUSH32 0x02 POP PUSH1 1 @end JUMP @end: STOP").unwrap(), metadata(0x19)].concat();
+        let diff = Diff::new(&old, &new, true);
+        assert!(diff.is_empty());
+        assert_eq!("", diff.to_text());
+
+        // PUSH32 values are only ignored when asked
+        assert!(!Diff::new(&old, &new, false).is_empty());
+    }
+
+    #[test]
+    fn jump_destinations() {
+        // PUSH1 0x04 JUMP STOP JUMPDEST STOP JUMPDEST STOP, jumping to the first JUMPDEST then the second
+        let old = [0x60, 0x04, 0x56, 0x00, 0x5b, 0x00, 0x5b, 0x00];
+        assert!(!Diff::new(&old, &[0x60, 0x06, 0x56, 0x00, 0x5b, 0x00, 0x5b, 0x00], false).is_empty());
+        // Or to offsets that aren't JUMPDESTs
+        assert!(!Diff::new(&[0x60, 0x05, 0x56, 0x00, 0x5b, 0x00, 0x5b, 0x00], &[0x60, 0x07, 0x56, 0x00, 0x5b, 0x00, 0x5b, 0x00], false).is_empty());
+
+        // The same JUMPDEST, moved
+        let old = assemble("@other @end JUMP @other: STOP @end: STOP").unwrap();
+        let new = assemble("@other @end JUMP @other: CALLER STOP @end: STOP").unwrap();
+        let diff = Diff::new(&old, &new, false);
+        assert_eq!(vec![Change::Added(4)], diff.changes.into_iter().filter(|change| !matches!(change, Change::Same { .. })).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn text() {
+        let old = assemble("CALLVALUE @revert JUMPI PUSH1 1 PUSH0 SSTORE STOP @revert: PUSH0 PUSH0 REVERT").unwrap();
+        let new = assemble("CALLVALUE @revert JUMPI PUSH1 2 PUSH0 SSTORE STOP @revert: PUSH0 PUSH0 REVERT").unwrap();
+        let expected = concat!(
+            "@@ 0004 -> 0004 @@\n",
+            "-0004      PUSH1 0x01\n",
+            "+     0004 PUSH1 0x02\n",
+            " 0006 0006 PUSH0\n",
+            " 0007 0007 SSTORE\n",
+            " 0008 0008 STOP\n",
+        );
+        assert_eq!(expected, Diff::new(&old, &new, false).to_text());
+    }
+}
@@ -0,0 +1,397 @@
+use std::collections::{ HashMap, HashSet };
+use std::fmt;
+
+use super::instructions::{ Instructions, OpCode };
+use super::metadata::Metadata;
+use super::program_context::encode_hex;
+
+// One decoded instruction. Bytes that aren't opcodes are kept, without a mnemonic, so the listing always
+// covers the whole of the code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisassembledInstruction {
+    pub offset: usize,
+    pub opcode: u8,
+    pub mnemonic: Option<&'static str>,
+    pub immediate: Vec<u8>, // PUSH data, cut short if the code ends first
+}
+
+impl DisassembledInstruction {
+    // Offset of the instruction that follows
+    pub fn next_offset(&self) -> usize {
+        self.offset + 1 + self.immediate.len()
+    }
+
+    // A PUSH whose data runs past the end of the code
+    pub fn is_truncated(&self) -> bool {
+        Instructions.get(&self.opcode).is_some_and(|instruction| self.immediate.len() < instruction.rom_items_used as usize)
+    }
+}
+
+// PUSH2 0x1234, or just the byte for anything that isn't an opcode
+impl fmt::Display for DisassembledInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.mnemonic {
+            Some(mnemonic) => write!(f, "{}", mnemonic)?,
+            None => write!(f, "{:#04x}", self.opcode)?,
+        }
+        if !self.immediate.is_empty() {
+            write!(f, " 0x{}", encode_hex(&self.immediate))?;
+        }
+        if self.is_truncated() {
+            write!(f, " (truncated)")?;
+        }
+        Ok(())
+    }
+}
+
+// Linear sweep over the whole code, decoding every byte as an instruction
+pub fn disassemble(code: &[u8]) -> Vec<DisassembledInstruction> {
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+    while offset < code.len() {
+        let instruction = decode(code, offset);
+        offset = instruction.next_offset();
+        instructions.push(instruction);
+    }
+    instructions
+}
+
+fn decode(code: &[u8], offset: usize) -> DisassembledInstruction {
+    let opcode = code[offset];
+    let instruction = Instructions.get(&opcode);
+    let immediate_size = instruction.map(|instruction| instruction.rom_items_used as usize).unwrap_or(0);
+    let end = usize::min(offset + 1 + immediate_size, code.len());
+    DisassembledInstruction {
+        offset,
+        opcode,
+        mnemonic: instruction.map(|instruction| instruction.mnemonic),
+        immediate: code[offset + 1..end].to_vec(),
+    }
+}
+
+// Execution never continues to the following instruction. Bytes that aren't opcodes halt as INVALID does.
+pub fn is_terminator(opcode: u8) -> bool {
+    const TERMINATORS: [u8; 6] = [OpCode::Stop as u8, OpCode::Jump as u8, OpCode::Return as u8, OpCode::Revert as u8, OpCode::Invalid as u8, OpCode::SelfDestruct as u8];
+    TERMINATORS.contains(&opcode) || !Instructions.contains_key(&opcode)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DataKind {
+    Unreachable, // e.g. a constructor's copy of the runtime code, or constructor arguments
+    Metadata,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataRegion {
+    pub offset: usize,
+    pub bytes: Vec<u8>,
+    pub kind: DataKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Code(DisassembledInstruction),
+    Data(DataRegion),
+}
+
+// Code split into instructions and the data between them, in order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disassembly {
+    pub segments: Vec<Segment>,
+    pub metadata: Option<Metadata>,
+}
+
+impl Disassembly {
+    // Solidity's trailing metadata is data. The rest is code if it's reachable from offset 0, following
+    // jumps to any JUMPDEST whose offset is pushed by reachable code, as internal calls push their return
+    // address long before jumping back to it. Everything else, such as the runtime code a constructor
+    // copies out, is data. Jumps to computed offsets aren't followed.
+    pub fn new(code: &[u8]) -> Disassembly {
+        let metadata = Metadata::from_code(code);
+        let code_end = metadata.as_ref().map(|metadata| metadata.offset).unwrap_or(code.len());
+        let reached = reachable(&code[..code_end]);
+
+        let mut segments = Vec::new();
+        let mut offset = 0;
+        while offset < code_end {
+            if !reached.contains(&offset) {
+                let end = (offset..code_end).find(|offset| reached.contains(offset)).unwrap_or(code_end);
+                segments.push(Segment::Data(DataRegion { offset, bytes: code[offset..end].to_vec(), kind: DataKind::Unreachable }));
+                offset = end;
+                continue;
+            }
+            let instruction = decode(&code[..code_end], offset);
+            offset = instruction.next_offset();
+            segments.push(Segment::Code(instruction));
+        }
+
+        if let Some(metadata) = &metadata {
+            segments.push(Segment::Data(DataRegion { offset: metadata.offset, bytes: code[metadata.offset..].to_vec(), kind: DataKind::Metadata }));
+        }
+        Disassembly { segments, metadata }
+    }
+
+    pub fn instructions(&self) -> impl Iterator<Item = &DisassembledInstruction> {
+        self.segments.iter().filter_map(|segment| match segment {
+            Segment::Code(instruction) => Some(instruction),
+            Segment::Data(_) => None,
+        })
+    }
+}
+
+// Offsets of the instructions reachable from the start of the code
+fn reachable(code: &[u8]) -> HashSet<usize> {
+    let jump_destinations: HashSet<usize> = disassemble(code).iter()
+        .filter(|instruction| instruction.opcode == OpCode::JumpDest as u8)
+        .map(|instruction| instruction.offset)
+        .collect();
+
+    let mut reached = HashSet::new();
+    let mut pending = if code.is_empty() { Vec::new() } else { vec![0] };
+    while let Some(mut offset) = pending.pop() {
+        while offset < code.len() && reached.insert(offset) {
+            let instruction = decode(code, offset);
+            if let Some(destination) = push_value(&instruction.immediate).filter(|value| jump_destinations.contains(value)) {
+                pending.push(destination);
+            }
+            if is_terminator(instruction.opcode) {
+                break;
+            }
+            offset = instruction.next_offset();
+        }
+    }
+    reached
+}
+
+// One instruction or data region per line, prefixed by its offset: 0000: PUSH2 0x1234
+pub fn to_text(disassembly: &Disassembly) -> String {
+    let mut text = String::new();
+    for segment in &disassembly.segments {
+        let line = match segment {
+            Segment::Code(instruction) => format!("{:04x}: {}", instruction.offset, instruction),
+            Segment::Data(region) => match (region.kind, &disassembly.metadata) {
+                (DataKind::Metadata, Some(metadata)) => format!("{:04x}: METADATA ({} bytes) {}", region.offset, region.bytes.len(), metadata),
+                _ => format!("{:04x}: DATA 0x{}", region.offset, encode_hex(&region.bytes)),
+            },
+        };
+        text.push_str(&line);
+        text.push('\n');
+    }
+    text
+}
+
+// An array of { offset, opcode, mnemonic, immediate } objects, with null for missing mnemonics and immediates.
+// Data regions are { offset, data, kind } objects, with any decoded metadata fields alongside.
+pub fn to_json(disassembly: &Disassembly) -> String {
+    let objects: Vec<String> = disassembly.segments.iter().map(|segment| match segment {
+        Segment::Code(instruction) => {
+            let mnemonic = instruction.mnemonic.map(|mnemonic| format!("\"{}\"", mnemonic)).unwrap_or_else(|| String::from("null"));
+            let immediate = if instruction.immediate.is_empty() {
+                String::from("null")
+            } else {
+                format!("\"0x{}\"", encode_hex(&instruction.immediate))
+            };
+            format!("  {{\"offset\": {}, \"opcode\": \"{:#04x}\", \"mnemonic\": {}, \"immediate\": {}}}", instruction.offset, instruction.opcode, mnemonic, immediate)
+        },
+        Segment::Data(region) => {
+            let mut fields = String::new();
+            if let (DataKind::Metadata, Some(metadata)) = (region.kind, &disassembly.metadata) {
+                for (name, value) in metadata.fields() {
+                    fields.push_str(&format!(", \"{}\": {}", name, json_string(&value)));
+                }
+            }
+            let kind = match region.kind {
+                DataKind::Unreachable => "unreachable",
+                DataKind::Metadata => "metadata",
+            };
+            format!("  {{\"offset\": {}, \"data\": \"0x{}\", \"kind\": \"{}\"{}}}", region.offset, encode_hex(&region.bytes), kind, fields)
+        },
+    }).collect();
+    if objects.is_empty() {
+        return String::from("[]\n");
+    }
+    format!("[\n{}\n]\n", objects.join(",\n"))
+}
+
+// Quoted, escaping what JSON requires. Metadata text comes from the code, so it can contain anything.
+fn json_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for character in value.chars() {
+        match character {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            character if character.is_control() => quoted.push_str(&format!("\\u{:04x}", character as u32)),
+            character => quoted.push(character),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// In the style of solc --asm: lowercase mnemonics, pushes as bare values and each JUMPDEST as a tag_n label.
+// Pushes feeding straight into a JUMP or JUMPI are written as the tag they jump to, metadata as auxdata.
+pub fn to_evmasm(disassembly: &Disassembly) -> String {
+    let instructions: Vec<&DisassembledInstruction> = disassembly.instructions().collect();
+    let tags: HashMap<usize, usize> = instructions.iter()
+        .filter(|instruction| instruction.opcode == OpCode::JumpDest as u8)
+        .enumerate()
+        .map(|(i, instruction)| (instruction.offset, i + 1))
+        .collect();
+
+    let mut asm = String::new();
+    let mut next = instructions.iter().skip(1);
+    for segment in &disassembly.segments {
+        let instruction = match segment {
+            Segment::Code(instruction) => instruction,
+            Segment::Data(region) => {
+                match region.kind {
+                    DataKind::Unreachable => asm.push_str(&format!("  /* data 0x{} */\n", encode_hex(&region.bytes))),
+                    DataKind::Metadata => asm.push_str(&format!("auxdata: 0x{}\n", encode_hex(&region.bytes))),
+                }
+                continue;
+            },
+        };
+        let following = next.next();
+        if let Some(tag) = tags.get(&instruction.offset) {
+            asm.push_str(&format!("tag_{}:\n", tag));
+            continue;
+        }
+        let line = match instruction.mnemonic {
+            Some(mnemonic) if mnemonic.starts_with("PUSH") => {
+                let jumps = following.is_some_and(|next| next.opcode == OpCode::Jump as u8 || next.opcode == OpCode::JumpI as u8);
+                let destination = jumps.then(|| tags.get(&push_value(&instruction.immediate)?)).flatten();
+                match destination {
+                    Some(tag) => format!("tag_{}", tag),
+                    None => compact_hex(&instruction.immediate),
+                }
+            },
+            Some(mnemonic) => mnemonic.to_lowercase(),
+            None => format!("/* {:#04x} */", instruction.opcode),
+        };
+        asm.push_str(&format!("  {}\n", line));
+    }
+    asm
+}
+
+fn significant_bytes(immediate: &[u8]) -> &[u8] {
+    &immediate[immediate.iter().take_while(|&&byte| byte == 0).count()..]
+}
+
+// Without leading zero bytes, as solc writes push values: 0x80, 0x0100, 0x00
+fn compact_hex(immediate: &[u8]) -> String {
+    match significant_bytes(immediate) {
+        [] => String::from("0x00"),
+        significant => format!("0x{}", encode_hex(significant)),
+    }
+}
+
+// The pushed value as an offset, if it's small enough to be one
+pub fn push_value(immediate: &[u8]) -> Option<usize> {
+    let significant = significant_bytes(immediate);
+    if significant.len() > 8 {
+        return None;
+    }
+    Some(significant.iter().fold(0usize, |value, &byte| (value << 8) | byte as usize))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disassemble_code() {
+        // PUSH2 0x1234 PUSH0 ADD 0x0c STOP
+        let instructions = disassemble(&[0x61, 0x12, 0x34, 0x5f, 0x01, 0x0c, 0x00]);
+        assert_eq!(vec![
+            DisassembledInstruction { offset: 0, opcode: 0x61, mnemonic: Some("PUSH2"), immediate: vec![0x12, 0x34] },
+            DisassembledInstruction { offset: 3, opcode: 0x5f, mnemonic: Some("PUSH0"), immediate: vec![] },
+            DisassembledInstruction { offset: 4, opcode: 0x01, mnemonic: Some("ADD"), immediate: vec![] },
+            DisassembledInstruction { offset: 5, opcode: 0x0c, mnemonic: None, immediate: vec![] },
+            DisassembledInstruction { offset: 6, opcode: 0x00, mnemonic: Some("STOP"), immediate: vec![] },
+        ], instructions);
+    }
+
+    #[test]
+    fn truncated_push() {
+        let instructions = disassemble(&[0x00, 0x63, 0x01, 0x02]);
+        assert_eq!(2, instructions.len());
+        assert_eq!(vec![0x01, 0x02], instructions[1].immediate);
+        assert_eq!(4, instructions[1].next_offset());
+        assert!(disassemble(&[]).is_empty());
+    }
+
+    #[test]
+    fn data_regions() {
+        // PUSH1 0x05 JUMP, unreachable 0x0c 0xaa, JUMPDEST STOP, then metadata { "solc": 0x000818 }
+        let metadata = [0xa1, 0x64, b's', b'o', b'l', b'c', 0x43, 0x00, 0x08, 0x18, 0x00, 0x0a];
+        let code = [vec![0x60, 0x05, 0x56, 0x0c, 0xaa, 0x5b, 0x00], metadata.to_vec()].concat();
+        let disassembly = Disassembly::new(&code);
+        assert_eq!(Some(String::from("0.8.24")), disassembly.metadata.as_ref().unwrap().solc);
+        assert_eq!(vec![
+            Segment::Code(DisassembledInstruction { offset: 0, opcode: 0x60, mnemonic: Some("PUSH1"), immediate: vec![0x05] }),
+            Segment::Code(DisassembledInstruction { offset: 2, opcode: 0x56, mnemonic: Some("JUMP"), immediate: vec![] }),
+            Segment::Data(DataRegion { offset: 3, bytes: vec![0x0c, 0xaa], kind: DataKind::Unreachable }),
+            Segment::Code(DisassembledInstruction { offset: 5, opcode: 0x5b, mnemonic: Some("JUMPDEST"), immediate: vec![] }),
+            Segment::Code(DisassembledInstruction { offset: 6, opcode: 0x00, mnemonic: Some("STOP"), immediate: vec![] }),
+            Segment::Data(DataRegion { offset: 7, bytes: metadata.to_vec(), kind: DataKind::Metadata }),
+        ], disassembly.segments);
+        assert_eq!(4, disassembly.instructions().count());
+
+        // A JUMPDEST nothing jumps to, like one in runtime code a constructor copies out, stays data
+        // PUSH1 0x03 PUSH1 0x0c PUSH1 0x00 CODECOPY PUSH1 0x03 PUSH1 0x00 RETURN, then STOP JUMPDEST STOP
+        let code = [0x60, 0x03, 0x60, 0x0c, 0x60, 0x00, 0x39, 0x60, 0x03, 0x60, 0x00, 0xf3, 0x00, 0x5b, 0x00];
+        let disassembly = Disassembly::new(&code);
+        assert_eq!(Some(&Segment::Data(DataRegion { offset: 12, bytes: vec![0x00, 0x5b, 0x00], kind: DataKind::Unreachable })), disassembly.segments.last());
+
+        // Return addresses pushed before an internal call are followed
+        // PUSH1 0x05 PUSH1 0x07 JUMP JUMPDEST STOP JUMPDEST JUMP
+        let disassembly = Disassembly::new(&[0x60, 0x05, 0x60, 0x07, 0x56, 0x5b, 0x00, 0x5b, 0x56]);
+        assert_eq!(7, disassembly.instructions().count());
+    }
+
+    #[test]
+    fn text() {
+        let disassembly = Disassembly::new(&[0x61, 0x12, 0x34, 0x01, 0x62, 0xaa]);
+        assert_eq!("0000: PUSH2 0x1234\n0003: ADD\n0004: PUSH3 0xaa (truncated)\n", to_text(&disassembly));
+
+        // STOP then data, and metadata { "solc": 0x000818 }
+        let disassembly = Disassembly::new(&[0x00, 0x0c, 0xa1, 0x64, b's', b'o', b'l', b'c', 0x43, 0x00, 0x08, 0x18, 0x00, 0x0a]);
+        assert_eq!("0000: STOP\n0001: DATA 0x0c\n0002: METADATA (12 bytes) solc 0.8.24\n", to_text(&disassembly));
+    }
+
+    #[test]
+    fn json() {
+        let disassembly = Disassembly::new(&[0x61, 0x12, 0x34, 0x0c, 0xfe]);
+        let expected = concat!(
+            "[\n",
+            "  {\"offset\": 0, \"opcode\": \"0x61\", \"mnemonic\": \"PUSH2\", \"immediate\": \"0x1234\"},\n",
+            "  {\"offset\": 3, \"opcode\": \"0x0c\", \"mnemonic\": null, \"immediate\": null},\n",
+            "  {\"offset\": 4, \"data\": \"0xfe\", \"kind\": \"unreachable\"}\n",
+            "]\n",
+        );
+        assert_eq!(expected, to_json(&disassembly));
+        assert_eq!("[]\n", to_json(&Disassembly::new(&[])));
+
+        // Metadata text is escaped, here { "solc": "\"x\n" }
+        let disassembly = Disassembly::new(&[0x00, 0xa1, 0x64, b's', b'o', b'l', b'c', 0x63, b'"', b'x', b'\n', 0x00, 0x0a]);
+        let expected = concat!(
+            "[\n",
+            "  {\"offset\": 0, \"opcode\": \"0x00\", \"mnemonic\": \"STOP\", \"immediate\": null},\n",
+            "  {\"offset\": 1, \"data\": \"0xa164736f6c636322780a000a\", \"kind\": \"metadata\", \"solc\": \"\\\"x\\n\"}\n",
+            "]\n",
+        );
+        assert_eq!(expected, to_json(&disassembly));
+        assert_eq!("\"a\\\\b\\u0000\"", json_string("a\\b\0"));
+    }
+
+    #[test]
+    fn evmasm() {
+        // PUSH1 0x80 PUSH2 0x0007 JUMP PUSH0 JUMPDEST STOP
+        let disassembly = Disassembly::new(&[0x60, 0x80, 0x61, 0x00, 0x07, 0x56, 0x5f, 0x5b, 0x00]);
+        assert_eq!("  0x80\n  tag_1\n  jump\n  /* data 0x5f */\ntag_1:\n  stop\n", to_evmasm(&disassembly));
+    }
+}
@@ -0,0 +1,73 @@
+use crate::consensus::Block;
+use super::types::{ u256, Address };
+
+// Protocol upgrades the interpreter distinguishes between, in activation order
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HardFork {
+    Shanghai,
+    #[default]
+    Cancun,
+}
+
+// Values describing the block the transaction is executed in
+#[derive(Debug, Clone, Default)]
+pub struct BlockEnv {
+    pub coinbase: Address,
+    pub timestamp: u256,
+    pub number: u256,
+    pub prev_randao: u256, // EIP-4399, replaces difficulty after the merge
+    pub gas_limit: u256,
+    pub chain_id: u256, // EIP-1344
+    pub base_fee: u256, // EIP-3198
+    pub blob_base_fee: u256, // EIP-7516
+}
+
+impl BlockEnv {
+    // The chain id isn't part of the header, it's a property of the network
+    pub fn from_header(header: &Block, chain_id: u256) -> BlockEnv {
+        BlockEnv {
+            coinbase: header.beneficiary,
+            timestamp: u256::from_u128(header.timestamp),
+            number: u256::from_u128(header.number),
+            prev_randao: header.mixHash.to_u256(),
+            gas_limit: u256::from_u128(header.gasLimit),
+            chain_id,
+            base_fee: u256::from_u128(header.baseFeePerGas),
+            blob_base_fee: u256::zero(), // Depends on the excess blob gas, which Block doesn't carry yet
+        }
+    }
+
+    // BLOCKHASH can only see the 256 most recent complete blocks
+    pub fn is_recent(&self, number: &u256) -> bool {
+        *number < self.number && self.number - *number <= u256::from_u128(256)
+    }
+}
+
+// 9.3. Execution Environment.
+//
+// The information the code is executed with, fixed for the duration of a call frame:
+//
+//     Ia, the address of the account which owns the code that is executing.
+//     Io, the sender address of the transaction that originated this execution.
+//     Ip, the price of gas paid by the signer of the transaction that originated this execution.
+//     Id, the byte array that is the input data to this execution; if the execution agent is a
+//         transaction, this would be the transaction data.
+//     Is, the address of the account which caused the code to be executing; if the execution agent is a
+//         transaction, this would be the transaction sender.
+//     Iv, the value, in Wei, passed to this account as part of the same procedure as execution; if the
+//         execution agent is a transaction, this would be the transaction value.
+//     Ie, the depth of the present message-call or contract-creation (i.e. the number of CALLs or
+//         CREATE(2)s being executed at present).
+//     Iw, the permission to make modifications to the state. Stored inverted, as is_static.
+#[derive(Debug, Clone, Default)]
+pub struct Environment {
+    pub address: Address,
+    pub origin: Address,
+    pub gas_price: u256,
+    pub calldata: Vec<u8>,
+    pub caller: Address,
+    pub value: u256,
+    pub depth: usize,
+    pub is_static: bool,
+    pub blob_hashes: Vec<u256>, // EIP-4844 versioned hashes of the transaction's blobs
+}
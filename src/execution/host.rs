@@ -0,0 +1,434 @@
+use std::collections::{ HashMap, HashSet };
+
+use crate::crypto::keccak256;
+use super::environment::{ BlockEnv, Environment, HardFork };
+use super::journal::{ Checkpoint, Journal, JournalEntry };
+use super::program_context::{ ProgramContext, Storage };
+use super::types::{ u256, Address, H256 };
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CallKind {
+    Call,
+    CallCode,
+    DelegateCall,
+    StaticCall,
+    Create,
+    Create2 { salt: u256 },
+}
+
+// EIP-170: limit on the size of deployed code
+pub const MAX_CODE_SIZE: usize = 0x6000;
+
+// Everything needed to start a new call frame. For creations the host derives Ia, so neither it nor the code
+// address is known up front.
+pub struct Message {
+    pub kind: CallKind,
+    pub environment: Environment, // Ia is the account whose storage and balance the frame acts on
+    pub code_address: Address, // Account whose code runs, only differs from Ia for CALLCODE and DELEGATECALL
+    pub block: BlockEnv,
+    pub gas: u64,
+}
+
+pub struct CallResult {
+    pub success: bool,
+    pub output: Vec<u8>, // RETURN or REVERT data
+    pub gas_left: u64,
+    pub created_address: Option<Address>, // Set by successful creations
+    pub logs: Vec<Log>, // Empty unless successful
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Log {
+    pub address: Address,
+    pub topics: Vec<H256>,
+    pub data: Vec<u8>,
+}
+
+// The interpreter's view of the world state, i.e. everything outside the executing call frame. Implement this
+// to execute against a different state backend.
+pub trait Host {
+    // EIP-161: no code, zero nonce and zero balance. Accounts that don't exist are also empty.
+    fn is_empty(&self, address: &Address) -> bool;
+
+    fn get_balance(&self, address: &Address) -> u256;
+
+    fn get_code(&self, address: &Address) -> Vec<u8>;
+
+    // KEC(b), σ[a]c in the yellow paper
+    fn get_code_hash(&self, address: &Address) -> H256;
+
+    fn sload(&self, address: &Address, key: &u256) -> u256;
+
+    fn sstore(&mut self, address: &Address, key: u256, value: u256);
+
+    // EIP-1153 transient storage, discarded by end_transaction
+    fn tload(&self, address: &Address, key: &u256) -> u256;
+
+    fn tstore(&mut self, address: &Address, key: u256, value: u256);
+
+    // Called with each log of a transaction that succeeds, once it's finished
+    fn emit_log(&mut self, log: Log);
+
+    fn call(&mut self, message: Message) -> CallResult;
+
+    // Runs the init code, deploying its output as the new account's code
+    fn create(&mut self, message: Message, init_code: Vec<u8>) -> CallResult;
+
+    // Moves the balance to the beneficiary, any deletion happens at the end of the transaction
+    fn selfdestruct(&mut self, address: &Address, beneficiary: &Address);
+
+    // Zero for unknown blocks. The interpreter restricts lookups to the 256 most recent.
+    fn block_hash(&self, number: &u256) -> H256;
+
+    // Marks the current state so everything changed after it can be undone
+    fn checkpoint(&mut self) -> Checkpoint;
+
+    fn revert_to(&mut self, checkpoint: Checkpoint);
+
+    // Applies deletions and discards anything scoped to the transaction, committing the journal
+    fn end_transaction(&mut self);
+}
+
+#[derive(Default)]
+pub struct Account {
+    pub nonce: u64,
+    pub balance: u256,
+    pub code: Vec<u8>,
+    pub storage: Storage,
+}
+
+impl Account {
+    pub fn is_empty(&self) -> bool {
+        self.nonce == 0 && self.balance.is_zero() && self.code.is_empty()
+    }
+}
+
+// World state held in memory, for tests and running code locally. Every mutation is journaled so failed
+// frames and transactions can be undone.
+#[derive(Default)]
+pub struct InMemoryHost {
+    accounts: HashMap<Address, Account>,
+    transient_storage: HashMap<Address, Storage>,
+    block_hashes: HashMap<u256, H256>,
+    logs: Vec<Log>,
+    hard_fork: HardFork,
+    created: HashSet<Address>, // Accounts created in the current transaction
+    destructed: HashSet<Address>, // Deleted when the transaction ends
+    journal: Journal,
+}
+
+impl InMemoryHost {
+    pub fn new() -> InMemoryHost {
+        InMemoryHost::default()
+    }
+
+    pub fn account(&self, address: &Address) -> Option<&Account> {
+        self.accounts.get(address)
+    }
+
+    // Creates the account if it doesn't exist. Meant for seeding state, as changes made through it aren't journaled.
+    pub fn account_mut(&mut self, address: &Address) -> &mut Account {
+        if !self.accounts.contains_key(address) {
+            self.journal.record(JournalEntry::AccountCreated { address: *address });
+        }
+        self.accounts.entry(*address).or_default()
+    }
+
+    pub fn set_code(&mut self, address: Address, code: Vec<u8>) {
+        let previous = std::mem::replace(&mut self.account_mut(&address).code, code);
+        self.journal.record(JournalEntry::CodeChanged { address, previous });
+    }
+
+    pub fn set_balance(&mut self, address: Address, balance: u256) {
+        let previous = std::mem::replace(&mut self.account_mut(&address).balance, balance);
+        self.journal.record(JournalEntry::BalanceChanged { address, previous });
+    }
+
+    pub fn set_nonce(&mut self, address: Address, nonce: u64) {
+        let previous = std::mem::replace(&mut self.account_mut(&address).nonce, nonce);
+        self.journal.record(JournalEntry::NonceChanged { address, previous });
+    }
+
+    pub fn set_hard_fork(&mut self, hard_fork: HardFork) {
+        self.hard_fork = hard_fork;
+    }
+
+    pub fn set_block_hash(&mut self, number: u256, hash: H256) {
+        self.block_hashes.insert(number, hash);
+    }
+
+    pub fn logs(&self) -> &[Log] {
+        &self.logs
+    }
+
+    fn transfer(&mut self, from: &Address, to: &Address, value: u256) {
+        self.set_balance(*from, self.get_balance(from) - value);
+        self.set_balance(*to, self.get_balance(to) + value);
+    }
+
+    // Entries are undone most recent first, so an account always exists until its creation is undone
+    fn undo(&mut self, entry: JournalEntry) {
+        let accounts = &mut self.accounts;
+        match entry {
+            JournalEntry::AccountCreated { address } => {
+                accounts.remove(&address);
+            },
+            JournalEntry::BalanceChanged { address, previous } => accounts.entry(address).or_default().balance = previous,
+            JournalEntry::NonceChanged { address, previous } => accounts.entry(address).or_default().nonce = previous,
+            JournalEntry::CodeChanged { address, previous } => accounts.entry(address).or_default().code = previous,
+            JournalEntry::StorageChanged { address, key, previous } => accounts.entry(address).or_default().storage.set(key, previous),
+            JournalEntry::TransientStorageChanged { address, key, previous } => {
+                self.transient_storage.entry(address).or_default().set(key, previous);
+            },
+            JournalEntry::ContractCreated { address } => {
+                self.created.remove(&address);
+            },
+            JournalEntry::Destructed { address } => {
+                self.destructed.remove(&address);
+            },
+        }
+    }
+
+    // Fails the creation, consuming all the gas
+    fn failed_creation() -> CallResult {
+        CallResult { success: false, output: Vec::new(), gas_left: 0, created_address: None, logs: Vec::new() }
+    }
+}
+
+impl Host for InMemoryHost {
+    fn is_empty(&self, address: &Address) -> bool {
+        self.accounts.get(address).is_none_or(|account| account.is_empty())
+    }
+
+    fn get_balance(&self, address: &Address) -> u256 {
+        self.accounts.get(address).map(|account| account.balance).unwrap_or_default()
+    }
+
+    fn get_code(&self, address: &Address) -> Vec<u8> {
+        self.accounts.get(address).map(|account| account.code.clone()).unwrap_or_default()
+    }
+
+    fn get_code_hash(&self, address: &Address) -> H256 {
+        H256(keccak256(&self.get_code(address)))
+    }
+
+    fn sload(&self, address: &Address, key: &u256) -> u256 {
+        self.accounts.get(address).map(|account| account.storage.get(key)).unwrap_or_default()
+    }
+
+    fn sstore(&mut self, address: &Address, key: u256, value: u256) {
+        let storage = &mut self.account_mut(address).storage;
+        let previous = storage.get(&key);
+        storage.set(key, value);
+        self.journal.record(JournalEntry::StorageChanged { address: *address, key, previous });
+    }
+
+    fn tload(&self, address: &Address, key: &u256) -> u256 {
+        self.transient_storage.get(address).map(|storage| storage.get(key)).unwrap_or_default()
+    }
+
+    fn tstore(&mut self, address: &Address, key: u256, value: u256) {
+        let storage = self.transient_storage.entry(*address).or_default();
+        let previous = storage.get(&key);
+        storage.set(key, value);
+        self.journal.record(JournalEntry::TransientStorageChanged { address: *address, key, previous });
+    }
+
+    fn emit_log(&mut self, log: Log) {
+        self.logs.push(log);
+    }
+
+    // The caller has already checked the sender can afford the value
+    fn call(&mut self, message: Message) -> CallResult {
+        let checkpoint = self.checkpoint();
+        let environment = &message.environment;
+        if message.kind != CallKind::DelegateCall && !environment.value.is_zero() {
+            self.transfer(&environment.caller, &environment.address, environment.value);
+        }
+
+        let code = self.get_code(&message.code_address);
+        let result = ProgramContext::execute_message(self, message, code);
+        if !result.success {
+            self.revert_to(checkpoint);
+        }
+        result
+    }
+
+    // The caller has already checked the sender can afford the value
+    fn create(&mut self, mut message: Message, init_code: Vec<u8>) -> CallResult {
+        let (caller, value) = (message.environment.caller, message.environment.value);
+        let nonce = self.account(&caller).map(|account| account.nonce).unwrap_or_default();
+        self.set_nonce(caller, nonce + 1); // Kept even if the creation fails
+        let address = match message.kind {
+            CallKind::Create2 { salt } => Address::from_create2(&caller, &salt, &init_code),
+            _ => Address::from_create(&caller, nonce),
+        };
+
+        // EIP-684: creating over an account with code or a nonce fails
+        let collision = self.accounts.get(&address).is_some_and(|account| account.nonce != 0 || !account.code.is_empty());
+        if collision {
+            return InMemoryHost::failed_creation();
+        }
+
+        let checkpoint = self.checkpoint();
+        self.transfer(&caller, &address, value);
+        self.set_nonce(address, 1); // EIP-161: new contracts start at nonce one
+        if self.created.insert(address) {
+            self.journal.record(JournalEntry::ContractCreated { address });
+        }
+        message.environment.address = address;
+        message.code_address = address;
+        let mut result = ProgramContext::execute_message(self, message, init_code);
+        if !result.success {
+            self.revert_to(checkpoint);
+            return result;
+        }
+
+        // EIP-170 limits the size of the deployed code, EIP-3541 reserves code starting with 0xef for EOF
+        let code = std::mem::take(&mut result.output);
+        if code.len() > MAX_CODE_SIZE || code.first() == Some(&0xef) {
+            self.revert_to(checkpoint);
+            return InMemoryHost::failed_creation();
+        }
+        self.set_code(address, code);
+        result.created_address = Some(address);
+        result
+    }
+
+    // A contract naming itself as beneficiary burns its balance if it's deleted, and keeps it otherwise
+    fn selfdestruct(&mut self, address: &Address, beneficiary: &Address) {
+        let balance = self.get_balance(address);
+        self.set_balance(*address, u256::zero());
+        self.set_balance(*beneficiary, self.get_balance(beneficiary) + balance);
+
+        // EIP-6780: from Cancun only contracts created in the same transaction are deleted
+        let deleted = self.hard_fork < HardFork::Cancun || self.created.contains(address);
+        if deleted && self.destructed.insert(*address) {
+            self.journal.record(JournalEntry::Destructed { address: *address });
+        }
+    }
+
+    fn block_hash(&self, number: &u256) -> H256 {
+        self.block_hashes.get(number).copied().unwrap_or_default()
+    }
+
+    fn checkpoint(&mut self) -> Checkpoint {
+        self.journal.checkpoint()
+    }
+
+    fn revert_to(&mut self, checkpoint: Checkpoint) {
+        for entry in self.journal.revert_to(checkpoint) {
+            self.undo(entry);
+        }
+    }
+
+    fn end_transaction(&mut self) {
+        for address in self.destructed.drain() {
+            self.accounts.remove(&address);
+        }
+        self.created.clear();
+        self.transient_storage.clear();
+        self.journal.commit();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_accounts() {
+        let mut host = InMemoryHost::new();
+        assert!(host.is_empty(&Address([0x01; 20])));
+        host.set_balance(Address([0x01; 20]), u256::one());
+        assert!(!host.is_empty(&Address([0x01; 20])));
+        host.set_code(Address([0x02; 20]), Vec::new());
+        assert!(host.is_empty(&Address([0x02; 20])));
+    }
+
+    #[test]
+    fn create() {
+        let mut host = InMemoryHost::new();
+        let sender = Address([0x01; 20]);
+        // Init code returning the single byte 0x00 as the deployed code
+        let init_code = vec![0x60, 0x01, 0x60, 0x00, 0xf3];
+        let message = |kind| Message {
+            kind,
+            environment: Environment { caller: sender, depth: 1, ..Environment::default() },
+            code_address: Address::zero(),
+            block: BlockEnv::default(),
+            gas: 100_000,
+        };
+
+        let result = host.create(message(CallKind::Create), init_code.clone());
+        let address = Address::from_create(&sender, 0);
+        assert!(result.success);
+        assert!(result.output.is_empty());
+        assert_eq!(Some(address), result.created_address);
+        assert_eq!(vec![0x00], host.get_code(&address));
+        assert_eq!(1, host.account(&address).unwrap().nonce);
+        assert_eq!(1, host.account(&sender).unwrap().nonce);
+
+        // The same salt and init code always derive the same address, so the second attempt collides
+        let create2 = CallKind::Create2 { salt: u256::one() };
+        let result = host.create(message(create2), init_code.clone());
+        assert_eq!(Some(Address::from_create2(&sender, &u256::one(), &init_code)), result.created_address);
+        let result = host.create(message(create2), init_code);
+        assert!(!result.success);
+        assert_eq!(0, result.gas_left);
+        assert_eq!(3, host.account(&sender).unwrap().nonce);
+    }
+
+    #[test]
+    fn create_code_checks() {
+        let mut host = InMemoryHost::new();
+        let message = || Message {
+            kind: CallKind::Create,
+            environment: Environment::default(),
+            code_address: Address::zero(),
+            block: BlockEnv::default(),
+            gas: 100_000,
+        };
+
+        // Copies its own final byte, 0xef, into memory and returns it
+        let init_code = vec![0x60, 0x01, 0x60, 0x0c, 0x60, 0x00, 0x39, 0x60, 0x01, 0x60, 0x00, 0xf3, 0xef];
+        let result = host.create(message(), init_code);
+        assert!(!result.success);
+        assert_eq!(None, result.created_address);
+        assert!(host.is_empty(&Address::from_create(&Address::zero(), 0)));
+
+        // Returns one byte more than the limit
+        let init_code = vec![0x61, 0x60, 0x01, 0x60, 0x00, 0xf3];
+        let result = host.create(message(), init_code);
+        assert!(!result.success);
+        assert!(host.is_empty(&Address::from_create(&Address::zero(), 1)));
+    }
+
+    #[test]
+    fn revert_to() {
+        let mut host = InMemoryHost::new();
+        host.set_balance(Address([0x01; 20]), u256::from_u8(5));
+        let checkpoint = host.checkpoint();
+        host.set_balance(Address([0x01; 20]), u256::from_u8(6));
+        host.sstore(&Address([0x01; 20]), u256::one(), u256::one());
+        host.tstore(&Address([0x01; 20]), u256::one(), u256::one());
+        host.set_code(Address([0x02; 20]), vec![0x00]);
+
+        host.revert_to(checkpoint);
+        assert_eq!(u256::from_u8(5), host.get_balance(&Address([0x01; 20])));
+        assert_eq!(u256::zero(), host.sload(&Address([0x01; 20]), &u256::one()));
+        assert_eq!(u256::zero(), host.tload(&Address([0x01; 20]), &u256::one()));
+        assert!(host.account(&Address([0x02; 20])).is_none());
+    }
+
+    #[test]
+    fn transient_storage() {
+        let mut host = InMemoryHost::new();
+        host.tstore(&Address([0x01; 20]), u256::one(), u256::from_u8(2));
+        assert_eq!(u256::from_u8(2), host.tload(&Address([0x01; 20]), &u256::one()));
+        assert_eq!(u256::zero(), host.tload(&Address([0x02; 20]), &u256::one()));
+        host.end_transaction();
+        assert_eq!(u256::zero(), host.tload(&Address([0x01; 20]), &u256::one()));
+    }
+}
@@ -1,10 +1,14 @@
 
 
+use std::cmp;
 use std::collections::HashMap;
 use std::fmt;
 
+use crate::crypto;
+use super::environment::Environment;
+use super::host::{ CallKind, Log, Message };
 use super::program_context::{ ProgramContext, ProgramError };
-use super::types::{ u256 };
+use super::types::{ u256, Address, H256 };
 
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub enum OpCode {
@@ -60,10 +64,13 @@ pub enum OpCode {
     Coinbase = 0x41,
     Timestamp = 0x42,
     Number = 0x43,
-    Difficulty = 0x44,
+    PrevRandao = 0x44, // DIFFICULTY before the merge
     GasLimit = 0x45,
     ChainId = 0x46,
     SelfBalance = 0x47,
+    BaseFee = 0x48,
+    BlobHash = 0x49,
+    BlobBaseFee = 0x4a,
     // 0x50: Stack, Memory, Storage and Flow Operations
     Pop = 0x50,
     MLoad = 0x51,
@@ -77,7 +84,11 @@ pub enum OpCode {
     MSize = 0x59,
     Gas = 0x5a,
     JumpDest = 0x5b,
-    // 0x60 and 0x70: Push Operations
+    TLoad = 0x5c,
+    TStore = 0x5d,
+    MCopy = 0x5e,
+    // 0x5f, 0x60 and 0x70: Push Operations
+    Push0 = 0x5f,
     Push1 = 0x60,
     Push2 = 0x61,
     Push3 = 0x62,
@@ -179,8 +190,8 @@ lazy_static! {
         (OpCode::Exp as u8, Instruction { value: OpCode::Exp as u8, mnemonic: "EXP", stack_items_removed: 2, stack_items_added: 1, rom_items_used: 0, execute: todo }),
         (OpCode::SignExtend as u8, Instruction { value: OpCode::SignExtend as u8, mnemonic: "SIGNEXTEND", stack_items_removed: 2, stack_items_added: 1, rom_items_used: 0, execute: todo }),
         // 0x10: Comparison and Bitwise Logic Operations
-        (OpCode::Lt as u8, Instruction { value: OpCode::Lt as u8, mnemonic: "LT", stack_items_removed: 2, stack_items_added: 1, rom_items_used: 0, execute: todo }),
-        (OpCode::Gt as u8, Instruction { value: OpCode::Gt as u8, mnemonic: "GT", stack_items_removed: 2, stack_items_added: 1, rom_items_used: 0, execute: todo }),
+        (OpCode::Lt as u8, Instruction { value: OpCode::Lt as u8, mnemonic: "LT", stack_items_removed: 2, stack_items_added: 1, rom_items_used: 0, execute: lt }),
+        (OpCode::Gt as u8, Instruction { value: OpCode::Gt as u8, mnemonic: "GT", stack_items_removed: 2, stack_items_added: 1, rom_items_used: 0, execute: gt }),
         (OpCode::Slt as u8, Instruction { value: OpCode::Slt as u8, mnemonic: "SLT", stack_items_removed: 2, stack_items_added: 1, rom_items_used: 0, execute: todo }),
         (OpCode::Sgt as u8, Instruction { value: OpCode::Sgt as u8, mnemonic: "SGT", stack_items_removed: 2, stack_items_added: 1, rom_items_used: 0, execute: todo }),
         (OpCode::Eq as u8, Instruction { value: OpCode::Eq as u8, mnemonic: "EQ", stack_items_removed: 2, stack_items_added: 1, rom_items_used: 0, execute: todo }),
@@ -194,47 +205,54 @@ lazy_static! {
         (OpCode::Shr as u8, Instruction { value: OpCode::Shr as u8, mnemonic: "SHR", stack_items_removed: 2, stack_items_added: 1, rom_items_used: 0, execute: todo }),
         (OpCode::Sar as u8, Instruction { value: OpCode::Sar as u8, mnemonic: "SAR", stack_items_removed: 2, stack_items_added: 1, rom_items_used: 0, execute: todo }),
         // 0x20: KECCAK256
-        (OpCode::Keccak256 as u8, Instruction { value: OpCode::Keccak256 as u8, mnemonic: "KECCAK256", stack_items_removed: 2, stack_items_added: 1, rom_items_used: 0, execute: todo }),
+        (OpCode::Keccak256 as u8, Instruction { value: OpCode::Keccak256 as u8, mnemonic: "KECCAK256", stack_items_removed: 2, stack_items_added: 1, rom_items_used: 0, execute: keccak256 }),
         // 0x30: Environmental Information
-        (OpCode::Address as u8, Instruction { value: OpCode::Address as u8, mnemonic: "ADDRESS", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: todo }),
-        (OpCode::Balance as u8, Instruction { value: OpCode::Balance as u8, mnemonic: "BALANCE", stack_items_removed: 1, stack_items_added: 1, rom_items_used: 0, execute: todo }),
-        (OpCode::Origin as u8, Instruction { value: OpCode::Origin as u8, mnemonic: "ORIGIN", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: todo }),
-        (OpCode::Caller as u8, Instruction { value: OpCode::Caller as u8, mnemonic: "CALLER", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: todo }),
-        (OpCode::CallValue as u8, Instruction { value: OpCode::CallValue as u8, mnemonic: "CALLVALUE", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: todo }),
-        (OpCode::CallDataLoad as u8, Instruction { value: OpCode::CallDataLoad as u8, mnemonic: "CALLDATALOAD", stack_items_removed: 1, stack_items_added: 1, rom_items_used: 0, execute: todo }),
-        (OpCode::CallDataSize as u8, Instruction { value: OpCode::CallDataSize as u8, mnemonic: "CALLDATASIZE", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: todo }),
-        (OpCode::CallDataCopy as u8, Instruction { value: OpCode::CallDataCopy as u8, mnemonic: "CALLDATACOPY", stack_items_removed: 3, stack_items_added: 0, rom_items_used: 0, execute: todo }),
-        (OpCode::CodeSize as u8, Instruction { value: OpCode::CodeSize as u8, mnemonic: "CODESIZE", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: todo }),
-        (OpCode::CodeCopy as u8, Instruction { value: OpCode::CodeCopy as u8, mnemonic: "CODECOPY", stack_items_removed: 3, stack_items_added: 0, rom_items_used: 0, execute: todo }),
-        (OpCode::GasPrice as u8, Instruction { value: OpCode::GasPrice as u8, mnemonic: "GASPRICE", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: todo }),
-        (OpCode::ExtCodeSize as u8, Instruction { value: OpCode::ExtCodeSize as u8, mnemonic: "EXTCODESIZE", stack_items_removed: 1, stack_items_added: 1, rom_items_used: 0, execute: todo }),
-        (OpCode::ExtCodeCopy as u8, Instruction { value: OpCode::ExtCodeCopy as u8, mnemonic: "EXTCODECOPY", stack_items_removed: 4, stack_items_added: 0, rom_items_used: 0, execute: todo }),
-        (OpCode::ReturnDataSize as u8, Instruction { value: OpCode::ReturnDataSize as u8, mnemonic: "RETURNDATASIZE", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: todo }),
-        (OpCode::ReturnDataCopy as u8, Instruction { value: OpCode::ReturnDataCopy as u8, mnemonic: "RETURNDATACOPY", stack_items_removed: 3, stack_items_added: 0, rom_items_used: 0, execute: todo }),
-        (OpCode::ExtCodeHash as u8, Instruction { value: OpCode::ExtCodeHash as u8, mnemonic: "EXTCODEHASH", stack_items_removed: 1, stack_items_added: 1, rom_items_used: 0, execute: todo }),
+        (OpCode::Address as u8, Instruction { value: OpCode::Address as u8, mnemonic: "ADDRESS", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: address }),
+        (OpCode::Balance as u8, Instruction { value: OpCode::Balance as u8, mnemonic: "BALANCE", stack_items_removed: 1, stack_items_added: 1, rom_items_used: 0, execute: balance }),
+        (OpCode::Origin as u8, Instruction { value: OpCode::Origin as u8, mnemonic: "ORIGIN", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: origin }),
+        (OpCode::Caller as u8, Instruction { value: OpCode::Caller as u8, mnemonic: "CALLER", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: caller }),
+        (OpCode::CallValue as u8, Instruction { value: OpCode::CallValue as u8, mnemonic: "CALLVALUE", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: call_value }),
+        (OpCode::CallDataLoad as u8, Instruction { value: OpCode::CallDataLoad as u8, mnemonic: "CALLDATALOAD", stack_items_removed: 1, stack_items_added: 1, rom_items_used: 0, execute: call_data_load }),
+        (OpCode::CallDataSize as u8, Instruction { value: OpCode::CallDataSize as u8, mnemonic: "CALLDATASIZE", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: call_data_size }),
+        (OpCode::CallDataCopy as u8, Instruction { value: OpCode::CallDataCopy as u8, mnemonic: "CALLDATACOPY", stack_items_removed: 3, stack_items_added: 0, rom_items_used: 0, execute: call_data_copy }),
+        (OpCode::CodeSize as u8, Instruction { value: OpCode::CodeSize as u8, mnemonic: "CODESIZE", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: code_size }),
+        (OpCode::CodeCopy as u8, Instruction { value: OpCode::CodeCopy as u8, mnemonic: "CODECOPY", stack_items_removed: 3, stack_items_added: 0, rom_items_used: 0, execute: code_copy }),
+        (OpCode::GasPrice as u8, Instruction { value: OpCode::GasPrice as u8, mnemonic: "GASPRICE", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: gas_price }),
+        (OpCode::ExtCodeSize as u8, Instruction { value: OpCode::ExtCodeSize as u8, mnemonic: "EXTCODESIZE", stack_items_removed: 1, stack_items_added: 1, rom_items_used: 0, execute: ext_code_size }),
+        (OpCode::ExtCodeCopy as u8, Instruction { value: OpCode::ExtCodeCopy as u8, mnemonic: "EXTCODECOPY", stack_items_removed: 4, stack_items_added: 0, rom_items_used: 0, execute: ext_code_copy }),
+        (OpCode::ReturnDataSize as u8, Instruction { value: OpCode::ReturnDataSize as u8, mnemonic: "RETURNDATASIZE", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: return_data_size }),
+        (OpCode::ReturnDataCopy as u8, Instruction { value: OpCode::ReturnDataCopy as u8, mnemonic: "RETURNDATACOPY", stack_items_removed: 3, stack_items_added: 0, rom_items_used: 0, execute: return_data_copy }),
+        (OpCode::ExtCodeHash as u8, Instruction { value: OpCode::ExtCodeHash as u8, mnemonic: "EXTCODEHASH", stack_items_removed: 1, stack_items_added: 1, rom_items_used: 0, execute: ext_code_hash }),
         // 0x40: Block Information
-        (OpCode::BlockHash as u8, Instruction { value: OpCode::BlockHash as u8, mnemonic: "BLOCKHASH", stack_items_removed: 1, stack_items_added: 1, rom_items_used: 0, execute: todo }),
-        (OpCode::Coinbase as u8, Instruction { value: OpCode::Coinbase as u8, mnemonic: "COINBASE", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: todo }),
-        (OpCode::Timestamp as u8, Instruction { value: OpCode::Timestamp as u8, mnemonic: "TIMESTAMP", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: todo }),
-        (OpCode::Number as u8, Instruction { value: OpCode::Number as u8, mnemonic: "NUMBER", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: todo }),
-        (OpCode::Difficulty as u8, Instruction { value: OpCode::Difficulty as u8, mnemonic: "DIFFICULTY", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: todo }),
-        (OpCode::GasLimit as u8, Instruction { value: OpCode::GasLimit as u8, mnemonic: "GASLIMIT", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: todo }),
-        (OpCode::ChainId as u8, Instruction { value: OpCode::ChainId as u8, mnemonic: "CHAINID", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: todo }),
-        (OpCode::SelfBalance as u8, Instruction { value: OpCode::SelfBalance as u8, mnemonic: "SELFBALANCE", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: todo }),
+        (OpCode::BlockHash as u8, Instruction { value: OpCode::BlockHash as u8, mnemonic: "BLOCKHASH", stack_items_removed: 1, stack_items_added: 1, rom_items_used: 0, execute: block_hash }),
+        (OpCode::Coinbase as u8, Instruction { value: OpCode::Coinbase as u8, mnemonic: "COINBASE", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: coinbase }),
+        (OpCode::Timestamp as u8, Instruction { value: OpCode::Timestamp as u8, mnemonic: "TIMESTAMP", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: timestamp }),
+        (OpCode::Number as u8, Instruction { value: OpCode::Number as u8, mnemonic: "NUMBER", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: number }),
+        (OpCode::PrevRandao as u8, Instruction { value: OpCode::PrevRandao as u8, mnemonic: "PREVRANDAO", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: prev_randao }),
+        (OpCode::GasLimit as u8, Instruction { value: OpCode::GasLimit as u8, mnemonic: "GASLIMIT", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: gas_limit }),
+        (OpCode::ChainId as u8, Instruction { value: OpCode::ChainId as u8, mnemonic: "CHAINID", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: chain_id }),
+        (OpCode::SelfBalance as u8, Instruction { value: OpCode::SelfBalance as u8, mnemonic: "SELFBALANCE", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: self_balance }),
+        (OpCode::BaseFee as u8, Instruction { value: OpCode::BaseFee as u8, mnemonic: "BASEFEE", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: base_fee }),
+        (OpCode::BlobHash as u8, Instruction { value: OpCode::BlobHash as u8, mnemonic: "BLOBHASH", stack_items_removed: 1, stack_items_added: 1, rom_items_used: 0, execute: blob_hash }),
+        (OpCode::BlobBaseFee as u8, Instruction { value: OpCode::BlobBaseFee as u8, mnemonic: "BLOBBASEFEE", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: blob_base_fee }),
         // 0x50: Stack, Memory, Storage and Flow Operations
         (OpCode::Pop as u8, Instruction { value: OpCode::Pop as u8, mnemonic: "POP", stack_items_removed: 1, stack_items_added: 0, rom_items_used: 0, execute: todo }),
         (OpCode::MLoad as u8, Instruction { value: OpCode::MLoad as u8, mnemonic: "MLOAD", stack_items_removed: 1, stack_items_added: 1, rom_items_used: 0, execute: todo }),
         (OpCode::MStore as u8, Instruction { value: OpCode::MStore as u8, mnemonic: "MSTORE", stack_items_removed: 2, stack_items_added: 0, rom_items_used: 0, execute: todo }),
         (OpCode::MStore8 as u8, Instruction { value: OpCode::MStore8 as u8, mnemonic: "MSTORE8", stack_items_removed: 2, stack_items_added: 0, rom_items_used: 0, execute: todo }),
-        (OpCode::SLoad as u8, Instruction { value: OpCode::SLoad as u8, mnemonic: "SLOAD", stack_items_removed: 1, stack_items_added: 1, rom_items_used: 0, execute: todo }),
-        (OpCode::SStore as u8, Instruction { value: OpCode::SStore as u8, mnemonic: "SSTORE", stack_items_removed: 2, stack_items_added: 0, rom_items_used: 0, execute: todo }),
+        (OpCode::SLoad as u8, Instruction { value: OpCode::SLoad as u8, mnemonic: "SLOAD", stack_items_removed: 1, stack_items_added: 1, rom_items_used: 0, execute: sload }),
+        (OpCode::SStore as u8, Instruction { value: OpCode::SStore as u8, mnemonic: "SSTORE", stack_items_removed: 2, stack_items_added: 0, rom_items_used: 0, execute: sstore }),
         (OpCode::Jump as u8, Instruction { value: OpCode::Jump as u8, mnemonic: "JUMP", stack_items_removed: 1, stack_items_added: 0, rom_items_used: 0, execute: todo }),
         (OpCode::JumpI as u8, Instruction { value: OpCode::JumpI as u8, mnemonic: "JUMPI", stack_items_removed: 2, stack_items_added: 0, rom_items_used: 0, execute: todo }),
         (OpCode::PC as u8, Instruction { value: OpCode::PC as u8, mnemonic: "PC", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: todo }),
         (OpCode::MSize as u8, Instruction { value: OpCode::MSize as u8, mnemonic: "MSIZE", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: todo }),
-        (OpCode::Gas as u8, Instruction { value: OpCode::Gas as u8, mnemonic: "GAS", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: todo }),
+        (OpCode::Gas as u8, Instruction { value: OpCode::Gas as u8, mnemonic: "GAS", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: gas }),
         (OpCode::JumpDest as u8, Instruction { value: OpCode::JumpDest as u8, mnemonic: "JUMPDEST", stack_items_removed: 0, stack_items_added: 0, rom_items_used: 0, execute: todo }),
-        // 0x60 and 0x70: Push Operations
+        (OpCode::TLoad as u8, Instruction { value: OpCode::TLoad as u8, mnemonic: "TLOAD", stack_items_removed: 1, stack_items_added: 1, rom_items_used: 0, execute: tload }),
+        (OpCode::TStore as u8, Instruction { value: OpCode::TStore as u8, mnemonic: "TSTORE", stack_items_removed: 2, stack_items_added: 0, rom_items_used: 0, execute: tstore }),
+        (OpCode::MCopy as u8, Instruction { value: OpCode::MCopy as u8, mnemonic: "MCOPY", stack_items_removed: 3, stack_items_added: 0, rom_items_used: 0, execute: mcopy }),
+        // 0x5f, 0x60 and 0x70: Push Operations
+        (OpCode::Push0 as u8, Instruction { value: OpCode::Push0 as u8, mnemonic: "PUSH0", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 0, execute: push }),
         (OpCode::Push1 as u8, Instruction { value: OpCode::Push1 as u8, mnemonic: "PUSH1", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 1, execute: push }),
         (OpCode::Push2 as u8, Instruction { value: OpCode::Push2 as u8, mnemonic: "PUSH2", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 2, execute: push }),
         (OpCode::Push3 as u8, Instruction { value: OpCode::Push3 as u8, mnemonic: "PUSH3", stack_items_removed: 0, stack_items_added: 1, rom_items_used: 3, execute: push }),
@@ -302,22 +320,22 @@ lazy_static! {
         (OpCode::Swap15 as u8, Instruction { value: OpCode::Swap15 as u8, mnemonic: "SWAP15", stack_items_removed: 16, stack_items_added: 16, rom_items_used: 0, execute: todo }),
         (OpCode::Swap16 as u8, Instruction { value: OpCode::Swap16 as u8, mnemonic: "SWAP16", stack_items_removed: 17, stack_items_added: 17, rom_items_used: 0, execute: todo }),
         // 0xa0: Logging Operations
-        (OpCode::Log0 as u8, Instruction { value: OpCode::Log0 as u8, mnemonic: "LOG0", stack_items_removed: 2, stack_items_added: 0, rom_items_used: 0, execute: todo }),
-        (OpCode::Log1 as u8, Instruction { value: OpCode::Log1 as u8, mnemonic: "LOG1", stack_items_removed: 3, stack_items_added: 0, rom_items_used: 0, execute: todo }),
-        (OpCode::Log2 as u8, Instruction { value: OpCode::Log2 as u8, mnemonic: "LOG2", stack_items_removed: 4, stack_items_added: 0, rom_items_used: 0, execute: todo }),
-        (OpCode::Log3 as u8, Instruction { value: OpCode::Log3 as u8, mnemonic: "LOG3", stack_items_removed: 5, stack_items_added: 0, rom_items_used: 0, execute: todo }),
-        (OpCode::Log4 as u8, Instruction { value: OpCode::Log4 as u8, mnemonic: "LOG4", stack_items_removed: 6, stack_items_added: 0, rom_items_used: 0, execute: todo }),
+        (OpCode::Log0 as u8, Instruction { value: OpCode::Log0 as u8, mnemonic: "LOG0", stack_items_removed: 2, stack_items_added: 0, rom_items_used: 0, execute: log }),
+        (OpCode::Log1 as u8, Instruction { value: OpCode::Log1 as u8, mnemonic: "LOG1", stack_items_removed: 3, stack_items_added: 0, rom_items_used: 0, execute: log }),
+        (OpCode::Log2 as u8, Instruction { value: OpCode::Log2 as u8, mnemonic: "LOG2", stack_items_removed: 4, stack_items_added: 0, rom_items_used: 0, execute: log }),
+        (OpCode::Log3 as u8, Instruction { value: OpCode::Log3 as u8, mnemonic: "LOG3", stack_items_removed: 5, stack_items_added: 0, rom_items_used: 0, execute: log }),
+        (OpCode::Log4 as u8, Instruction { value: OpCode::Log4 as u8, mnemonic: "LOG4", stack_items_removed: 6, stack_items_added: 0, rom_items_used: 0, execute: log }),
         // 0xf0: System Operations
-        (OpCode::Create as u8, Instruction { value: OpCode::Create as u8, mnemonic: "CREATE", stack_items_removed: 3, stack_items_added: 1, rom_items_used: 0, execute: todo }),
-        (OpCode::Call as u8, Instruction { value: OpCode::Call as u8, mnemonic: "CALL", stack_items_removed: 7, stack_items_added: 1, rom_items_used: 0, execute: todo }),
-        (OpCode::CallCode as u8, Instruction { value: OpCode::CallCode as u8, mnemonic: "CALLCODE", stack_items_removed: 7, stack_items_added: 1, rom_items_used: 0, execute: todo }),
-        (OpCode::Return as u8, Instruction { value: OpCode::Return as u8, mnemonic: "RETURN", stack_items_removed: 2, stack_items_added: 0, rom_items_used: 0, execute: todo }),
-        (OpCode::DelegateCall as u8, Instruction { value: OpCode::DelegateCall as u8, mnemonic: "DELEGATECALL", stack_items_removed: 6, stack_items_added: 1, rom_items_used: 0, execute: todo }),
-        (OpCode::Create2 as u8, Instruction { value: OpCode::Create2 as u8, mnemonic: "CREATE2", stack_items_removed: 4, stack_items_added: 1, rom_items_used: 0, execute: todo }),
-        (OpCode::StaticCall as u8, Instruction { value: OpCode::StaticCall as u8, mnemonic: "STATICCALL", stack_items_removed: 6, stack_items_added: 1, rom_items_used: 0, execute: todo }),
-        (OpCode::Revert as u8, Instruction { value: OpCode::Revert as u8, mnemonic: "REVERT", stack_items_removed: 2, stack_items_added: 0, rom_items_used: 0, execute: todo }),
+        (OpCode::Create as u8, Instruction { value: OpCode::Create as u8, mnemonic: "CREATE", stack_items_removed: 3, stack_items_added: 1, rom_items_used: 0, execute: create }),
+        (OpCode::Call as u8, Instruction { value: OpCode::Call as u8, mnemonic: "CALL", stack_items_removed: 7, stack_items_added: 1, rom_items_used: 0, execute: call }),
+        (OpCode::CallCode as u8, Instruction { value: OpCode::CallCode as u8, mnemonic: "CALLCODE", stack_items_removed: 7, stack_items_added: 1, rom_items_used: 0, execute: call_code }),
+        (OpCode::Return as u8, Instruction { value: OpCode::Return as u8, mnemonic: "RETURN", stack_items_removed: 2, stack_items_added: 0, rom_items_used: 0, execute: f_return }),
+        (OpCode::DelegateCall as u8, Instruction { value: OpCode::DelegateCall as u8, mnemonic: "DELEGATECALL", stack_items_removed: 6, stack_items_added: 1, rom_items_used: 0, execute: delegate_call }),
+        (OpCode::Create2 as u8, Instruction { value: OpCode::Create2 as u8, mnemonic: "CREATE2", stack_items_removed: 4, stack_items_added: 1, rom_items_used: 0, execute: create2 }),
+        (OpCode::StaticCall as u8, Instruction { value: OpCode::StaticCall as u8, mnemonic: "STATICCALL", stack_items_removed: 6, stack_items_added: 1, rom_items_used: 0, execute: static_call }),
+        (OpCode::Revert as u8, Instruction { value: OpCode::Revert as u8, mnemonic: "REVERT", stack_items_removed: 2, stack_items_added: 0, rom_items_used: 0, execute: revert }),
         (OpCode::Invalid as u8, Instruction { value: OpCode::Invalid as u8, mnemonic: "INVALID", stack_items_removed: 0, stack_items_added: 0, rom_items_used: 0, execute: todo }),
-        (OpCode::SelfDestruct as u8, Instruction { value: OpCode::SelfDestruct as u8, mnemonic: "SELFDESTRUCT", stack_items_removed: 1, stack_items_added: 0, rom_items_used: 0, execute: todo }),
+        (OpCode::SelfDestruct as u8, Instruction { value: OpCode::SelfDestruct as u8, mnemonic: "SELFDESTRUCT", stack_items_removed: 1, stack_items_added: 0, rom_items_used: 0, execute: selfdestruct }),
     ]);
 }
 
@@ -430,16 +448,995 @@ fn gt(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramEr
 }
 
 
-//
+// 0x20: KECCAK256
+fn keccak256(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    let data = pop_memory_range(program_context)?;
+    program_context.stack.push(u256::from_be_bytes(&crypto::keccak256(&data)));
+    Ok(())
+}
+
+// 0x30: Environmental Information
+fn address(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    program_context.stack.push(program_context.environment.address.to_u256());
+    Ok(())
+}
+
+fn balance(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    let address = Address::from_u256(program_context.stack.pop());
+    program_context.stack.push(program_context.host.get_balance(&address));
+    Ok(())
+}
+
+fn origin(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    program_context.stack.push(program_context.environment.origin.to_u256());
+    Ok(())
+}
+
+fn caller(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    program_context.stack.push(program_context.environment.caller.to_u256());
+    Ok(())
+}
+
+fn call_value(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    program_context.stack.push(program_context.environment.value);
+    Ok(())
+}
+
+fn call_data_load(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    let offset = program_context.stack.pop();
+    let data = padded_slice(&program_context.environment.calldata, offset, 32);
+    program_context.stack.push(u256::from_be_bytes(&data));
+    Ok(())
+}
+
+fn call_data_size(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    program_context.stack.push(u256::from_u128(program_context.environment.calldata.len() as u128));
+    Ok(())
+}
+
+fn call_data_copy(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    let destination = program_context.stack.pop();
+    let offset = program_context.stack.pop();
+    let size = program_context.stack.pop();
+    if size.is_zero() {
+        return Ok(());
+    }
+    let data = padded_slice(&program_context.environment.calldata, offset, to_memory_offset(size)?);
+    program_context.memory.store(to_memory_offset(destination)?, &data)
+}
+
+fn code_size(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    program_context.stack.push(u256::from_u128(program_context.rom.code().len() as u128));
+    Ok(())
+}
+
+fn code_copy(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    let destination = program_context.stack.pop();
+    let offset = program_context.stack.pop();
+    let size = program_context.stack.pop();
+    if size.is_zero() {
+        return Ok(());
+    }
+    let data = padded_slice(program_context.rom.code(), offset, to_memory_offset(size)?);
+    program_context.memory.store(to_memory_offset(destination)?, &data)
+}
+
+fn gas_price(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    program_context.stack.push(program_context.environment.gas_price);
+    Ok(())
+}
+
+fn ext_code_size(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    let address = Address::from_u256(program_context.stack.pop());
+    let size = program_context.host.get_code(&address).len();
+    program_context.stack.push(u256::from_u128(size as u128));
+    Ok(())
+}
+
+fn ext_code_copy(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    let address = Address::from_u256(program_context.stack.pop());
+    let destination = program_context.stack.pop();
+    let offset = program_context.stack.pop();
+    let size = program_context.stack.pop();
+    if size.is_zero() {
+        return Ok(());
+    }
+    let data = padded_slice(&program_context.host.get_code(&address), offset, to_memory_offset(size)?);
+    program_context.memory.store(to_memory_offset(destination)?, &data)
+}
+
+// EIP-1052: empty accounts hash to zero, while an account without code has the hash of the empty string
+fn ext_code_hash(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    let address = Address::from_u256(program_context.stack.pop());
+    let hash = if program_context.host.is_empty(&address) {
+        u256::zero()
+    } else {
+        program_context.host.get_code_hash(&address).to_u256()
+    };
+    program_context.stack.push(hash);
+    Ok(())
+}
+
+fn return_data_size(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    program_context.stack.push(u256::from_u128(program_context.return_data.len() as u128));
+    Ok(())
+}
+
+// Unlike the other copies this doesn't zero pad, EIP-211 makes reading past the end an exceptional halt
+fn return_data_copy(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    let destination = program_context.stack.pop();
+    let offset = program_context.stack.pop();
+    let size = program_context.stack.pop();
+    let (offset, size) = match (offset.to_usize(), size.to_usize()) {
+        (Some(offset), Some(size)) => (offset, size),
+        _ => return Err(ProgramError::ReturnDataOutOfBounds),
+    };
+    if offset.checked_add(size).is_none_or(|end| end > program_context.return_data.len()) {
+        return Err(ProgramError::ReturnDataOutOfBounds);
+    }
+    if size == 0 {
+        return Ok(());
+    }
+    let data = program_context.return_data[offset..offset + size].to_vec();
+    program_context.memory.store(to_memory_offset(destination)?, &data)
+}
+
+// 0x40: Block Information
+fn block_hash(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    let number = program_context.stack.pop();
+    let hash = if program_context.block.is_recent(&number) {
+        program_context.host.block_hash(&number).to_u256()
+    } else {
+        u256::zero()
+    };
+    program_context.stack.push(hash);
+    Ok(())
+}
+
+fn coinbase(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    program_context.stack.push(program_context.block.coinbase.to_u256());
+    Ok(())
+}
+
+fn timestamp(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    program_context.stack.push(program_context.block.timestamp);
+    Ok(())
+}
+
+fn number(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    program_context.stack.push(program_context.block.number);
+    Ok(())
+}
+
+fn prev_randao(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    program_context.stack.push(program_context.block.prev_randao);
+    Ok(())
+}
+
+fn gas_limit(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    program_context.stack.push(program_context.block.gas_limit);
+    Ok(())
+}
+
+fn chain_id(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    program_context.stack.push(program_context.block.chain_id);
+    Ok(())
+}
+
+fn self_balance(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    program_context.stack.push(program_context.host.get_balance(&program_context.environment.address));
+    Ok(())
+}
+
+fn base_fee(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    program_context.stack.push(program_context.block.base_fee);
+    Ok(())
+}
+
+fn blob_hash(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    let index = program_context.stack.pop();
+    let hash = index.to_usize()
+        .and_then(|index| program_context.environment.blob_hashes.get(index).copied())
+        .unwrap_or_default(); // Out of range indexes give zero rather than failing
+    program_context.stack.push(hash);
+    Ok(())
+}
+
+fn blob_base_fee(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    program_context.stack.push(program_context.block.blob_base_fee);
+    Ok(())
+}
+
+// 0x50: Stack, Memory, Storage and Flow Operations
+fn sload(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    let key = program_context.stack.pop();
+    let value = program_context.host.sload(&program_context.environment.address, &key);
+    program_context.stack.push(value);
+    Ok(())
+}
+
+fn sstore(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    if program_context.environment.is_static {
+        return Err(ProgramError::StaticStateChange);
+    }
+    let key = program_context.stack.pop();
+    let value = program_context.stack.pop();
+    program_context.host.sstore(&program_context.environment.address, key, value);
+    Ok(())
+}
+
+fn tload(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    let key = program_context.stack.pop();
+    let value = program_context.host.tload(&program_context.environment.address, &key);
+    program_context.stack.push(value);
+    Ok(())
+}
+
+fn tstore(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    if program_context.environment.is_static {
+        return Err(ProgramError::StaticStateChange);
+    }
+    let key = program_context.stack.pop();
+    let value = program_context.stack.pop();
+    program_context.host.tstore(&program_context.environment.address, key, value);
+    Ok(())
+}
+
+fn gas(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    program_context.stack.push(u256::from_u128(program_context.gas as u128));
+    Ok(())
+}
+
+fn mcopy(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    let destination = program_context.stack.pop();
+    let source = program_context.stack.pop();
+    let size = program_context.stack.pop();
+    if size.is_zero() {
+        return Ok(());
+    }
+    program_context.memory.copy(to_memory_offset(destination)?, to_memory_offset(source)?, to_memory_offset(size)?)
+}
+
+// 0x5f, 0x60 and 0x70: Push Operations
 fn push(opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
-    let mut push_num = opcode + 1 - (OpCode::Push1 as u8); // Get number of pushes to make based upon opcode offset from push1
-    let mut data: u256 = u256::zero();
-    while push_num > 0 { // This could be much more efficient if a slice is returned instead...
-         let byte_data: u256 = u256::from_u8(program_context.rom.next_byte()?);
-         //data += byte_data << ((push_num-1) * 8);
-         push_num -= 1;
-         //println!("{:32x}", data);
+    let push_num = opcode + 1 - (OpCode::Push1 as u8); // Get number of bytes to push based upon opcode offset from push1, PUSH0 has none
+    let mut data: Vec<u8> = Vec::with_capacity(push_num as usize);
+    for _ in 0..push_num {
+        data.push(program_context.rom.next_byte()?);
+    }
+    program_context.stack.push(u256::from_be_bytes(&data));
+    Ok(())
+}
+
+// 0xa0: Logging Operations
+fn log(opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    if program_context.environment.is_static {
+        return Err(ProgramError::StaticStateChange);
     }
-    program_context.stack.push(data);
+    let data = pop_memory_range(program_context)?;
+    let topic_count = opcode - (OpCode::Log0 as u8);
+    let topics = (0..topic_count).map(|_| H256::from_u256(program_context.stack.pop())).collect();
+    program_context.logs.push(Log { address: program_context.environment.address, topics, data });
     Ok(())
 }
+
+// 0xf0: System Operations
+pub const CALL_DEPTH_LIMIT: usize = 1024;
+pub const CALL_STIPEND: u64 = 2300; // Free gas given to the callee when value is sent
+
+fn call(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    message_call(CallKind::Call, program_context)
+}
+
+fn call_code(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    message_call(CallKind::CallCode, program_context)
+}
+
+fn delegate_call(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    message_call(CallKind::DelegateCall, program_context)
+}
+
+fn static_call(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    message_call(CallKind::StaticCall, program_context)
+}
+
+// The call opcodes only differ in their arguments and the environment the callee gets
+fn message_call(kind: CallKind, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    let requested_gas = program_context.stack.pop();
+    let address = Address::from_u256(program_context.stack.pop());
+    let value = match kind {
+        CallKind::Call | CallKind::CallCode => program_context.stack.pop(),
+        _ => u256::zero(),
+    };
+    let args_offset = program_context.stack.pop();
+    let args_size = program_context.stack.pop();
+    let ret_offset = program_context.stack.pop();
+    let ret_size = program_context.stack.pop();
+
+    if kind == CallKind::Call && !value.is_zero() && program_context.environment.is_static {
+        return Err(ProgramError::StaticStateChange);
+    }
+
+    let input = if args_size.is_zero() {
+        Vec::new()
+    } else {
+        program_context.memory.load(to_memory_offset(args_offset)?, to_memory_offset(args_size)?)?
+    };
+    let (ret_offset, ret_size) = if ret_size.is_zero() {
+        (0, 0)
+    } else {
+        (to_memory_offset(ret_offset)?, to_memory_offset(ret_size)?)
+    };
+    program_context.memory.expand(ret_offset, ret_size)?;
+
+    // Failing these doesn't halt the caller, the call just doesn't happen
+    let balance = program_context.host.get_balance(&program_context.environment.address);
+    program_context.return_data.clear();
+    if program_context.environment.depth >= CALL_DEPTH_LIMIT || value > balance {
+        program_context.stack.push(u256::zero());
+        return Ok(());
+    }
+
+    // EIP-150: all but one 64th of the remaining gas can be forwarded
+    let available_gas = program_context.gas - program_context.gas / 64;
+    let mut gas = cmp::min(requested_gas.to_u64().unwrap_or(u64::MAX), available_gas);
+    program_context.gas -= gas;
+    if !value.is_zero() {
+        gas += CALL_STIPEND;
+    }
+
+    let parent = &program_context.environment;
+    let environment = Environment {
+        address: match kind {
+            CallKind::Call | CallKind::StaticCall => address,
+            _ => parent.address,
+        },
+        caller: match kind {
+            CallKind::DelegateCall => parent.caller,
+            _ => parent.address,
+        },
+        value: match kind {
+            CallKind::DelegateCall => parent.value,
+            _ => value,
+        },
+        calldata: input,
+        depth: parent.depth + 1,
+        is_static: parent.is_static || kind == CallKind::StaticCall,
+        ..parent.clone()
+    };
+    let message = Message { kind, environment, code_address: address, block: program_context.block.clone(), gas };
+    let result = program_context.call(message);
+
+    program_context.gas += result.gas_left;
+    let size = cmp::min(ret_size, result.output.len());
+    program_context.memory.store(ret_offset, &result.output[..size])?;
+    program_context.return_data = result.output;
+    program_context.logs.extend(result.logs);
+    program_context.stack.push(if result.success { u256::one() } else { u256::zero() });
+    Ok(())
+}
+
+fn create(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    let value = program_context.stack.pop();
+    let init_code = pop_memory_range(program_context)?;
+    contract_creation(CallKind::Create, value, init_code, program_context)
+}
+
+fn create2(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    let value = program_context.stack.pop();
+    let init_code = pop_memory_range(program_context)?;
+    let salt = program_context.stack.pop();
+    contract_creation(CallKind::Create2 { salt }, value, init_code, program_context)
+}
+
+// Shared by CREATE and CREATE2, pushes the new account's address or zero on failure
+fn contract_creation(kind: CallKind, value: u256, init_code: Vec<u8>, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    if program_context.environment.is_static {
+        return Err(ProgramError::StaticStateChange);
+    }
+
+    let balance = program_context.host.get_balance(&program_context.environment.address);
+    program_context.return_data.clear();
+    if program_context.environment.depth >= CALL_DEPTH_LIMIT || value > balance {
+        program_context.stack.push(u256::zero());
+        return Ok(());
+    }
+
+    let gas = program_context.gas - program_context.gas / 64;
+    program_context.gas -= gas;
+
+    let parent = &program_context.environment;
+    let environment = Environment {
+        address: Address::zero(), // Derived by the host
+        caller: parent.address,
+        value,
+        calldata: Vec::new(),
+        depth: parent.depth + 1,
+        ..parent.clone()
+    };
+    let message = Message { kind, environment, code_address: Address::zero(), block: program_context.block.clone(), gas };
+    let result = program_context.host.create(message, init_code);
+
+    program_context.gas += result.gas_left;
+    program_context.return_data = result.output; // Only a revert's data, deployed code isn't returned
+    program_context.logs.extend(result.logs);
+    program_context.stack.push(result.created_address.map(|address| address.to_u256()).unwrap_or_default());
+    Ok(())
+}
+
+fn f_return(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    program_context.output = pop_memory_range(program_context)?;
+    Err(ProgramError::Returned)
+}
+
+fn revert(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    program_context.output = pop_memory_range(program_context)?;
+    Err(ProgramError::Reverted)
+}
+
+fn selfdestruct(_opcode: u8, program_context: &mut ProgramContext) -> Result<(), ProgramError> {
+    if program_context.environment.is_static {
+        return Err(ProgramError::StaticStateChange);
+    }
+    let beneficiary = Address::from_u256(program_context.stack.pop());
+    program_context.host.selfdestruct(&program_context.environment.address, &beneficiary);
+    Err(ProgramError::Stopped)
+}
+
+// Pops an offset and size from the stack, returning that region of memory
+fn pop_memory_range(program_context: &mut ProgramContext) -> Result<Vec<u8>, ProgramError> {
+    let offset = program_context.stack.pop();
+    let size = program_context.stack.pop();
+    if size.is_zero() {
+        return Ok(Vec::new());
+    }
+    program_context.memory.load(to_memory_offset(offset)?, to_memory_offset(size)?)
+}
+
+// Reads size bytes from offset, anything past the end of data reads as zero
+fn padded_slice(data: &[u8], offset: u256, size: usize) -> Vec<u8> {
+    let mut slice = vec![0u8; size];
+    if let Some(offset) = offset.to_usize() {
+        if offset < data.len() {
+            let end = cmp::min(data.len(), offset.saturating_add(size));
+            slice[..end - offset].copy_from_slice(&data[offset..end]);
+        }
+    }
+    slice
+}
+
+// Memory offsets and sizes too large for a usize could never be allocated
+fn to_memory_offset(value: u256) -> Result<usize, ProgramError> {
+    value.to_usize().ok_or(ProgramError::MemoryOutOfBounds)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::Block;
+    use crate::execution::environment::{ BlockEnv, Environment, HardFork };
+    use crate::execution::host::{ Host, InMemoryHost };
+    use crate::execution::precompiles::precompile_address;
+    use crate::execution::program_context::encode_hex;
+    use crate::execution::program_context::Rom;
+    use crate::execution::types::{ Address, H256 };
+
+    fn run<'a>(code: &str, host: &'a mut InMemoryHost) -> ProgramContext<'a> {
+        let mut program_context = ProgramContext::new(Rom::from_hex(code).unwrap(), Environment::default(), host);
+        program_context.run().unwrap();
+        program_context
+    }
+
+    #[test]
+    fn push() {
+        // PUSH0 PUSH2 0x1234
+        let mut host = InMemoryHost::new();
+        let mut program_context = run("5f611234", &mut host);
+        assert_eq!(u256::from_u128(0x1234), program_context.stack.pop());
+        assert_eq!(u256::zero(), program_context.stack.pop());
+    }
+
+    #[test]
+    fn transient_storage() {
+        // PUSH1 0x2a PUSH1 0x01 TSTORE PUSH1 0x01 TLOAD
+        let mut host = InMemoryHost::new();
+        let mut program_context = ProgramContext::new(Rom::from_hex("602a60015d60015c").unwrap(), Environment::default(), &mut host);
+        for _ in 0..5 {
+            program_context.step().unwrap();
+        }
+        assert_eq!(u256::from_u8(0x2a), program_context.stack.pop());
+
+        // Cleared once the transaction ends
+        program_context.run().unwrap();
+        assert_eq!(u256::zero(), program_context.host.tload(&Address::zero(), &u256::one()));
+    }
+
+    #[test]
+    fn mcopy() {
+        let mut host = InMemoryHost::new();
+        let mut program_context = ProgramContext::new(Rom::from_hex("5e").unwrap(), Environment::default(), &mut host);
+        program_context.memory.store(0, &[1, 2, 3, 4]).unwrap();
+        // Overlapping copy of 4 bytes from 0 to 2
+        program_context.stack.push(u256::from_u8(4));
+        program_context.stack.push(u256::zero());
+        program_context.stack.push(u256::from_u8(2));
+        program_context.run().unwrap();
+        assert_eq!(vec![1, 2, 1, 2, 3, 4], program_context.memory.load(0, 6).unwrap());
+        assert_eq!(32, program_context.memory.size());
+    }
+
+    #[test]
+    fn keccak256() {
+        // PUSH1 0x03 PUSH1 0x00 KECCAK256 PUSH1 0x00 PUSH1 0x00 KECCAK256
+        let mut host = InMemoryHost::new();
+        let mut program_context = ProgramContext::new(Rom::from_hex("6003600020600060002000").unwrap(), Environment::default(), &mut host);
+        program_context.memory.store(0, b"abc").unwrap();
+        program_context.run().unwrap();
+        assert_eq!(u256::from_be_bytes(&crypto::keccak256(b"")), program_context.stack.pop());
+        assert_eq!(u256::from_be_bytes(&crypto::keccak256(b"abc")), program_context.stack.pop());
+    }
+
+    #[test]
+    fn environment() {
+        // ADDRESS CALLER ORIGIN CALLVALUE GASPRICE
+        let environment = Environment {
+            address: Address([0x01; 20]),
+            caller: Address([0x02; 20]),
+            origin: Address([0x03; 20]),
+            value: u256::from_u8(4),
+            gas_price: u256::from_u8(5),
+            ..Environment::default()
+        };
+        let mut host = InMemoryHost::new();
+        let mut program_context = ProgramContext::new(Rom::from_hex("303332343a").unwrap(), environment, &mut host);
+        program_context.run().unwrap();
+        assert_eq!(u256::from_u8(5), program_context.stack.pop());
+        assert_eq!(u256::from_u8(4), program_context.stack.pop());
+        assert_eq!(Address([0x03; 20]).to_u256(), program_context.stack.pop());
+        assert_eq!(Address([0x02; 20]).to_u256(), program_context.stack.pop());
+        assert_eq!(Address([0x01; 20]).to_u256(), program_context.stack.pop());
+    }
+
+    #[test]
+    fn calldata() {
+        // CALLDATASIZE PUSH1 0x02 CALLDATALOAD PUSH1 0x04 PUSH1 0x01 PUSH1 0x00 CALLDATACOPY
+        let environment = Environment { calldata: vec![0x11, 0x22, 0x33, 0x44], ..Environment::default() };
+        let mut host = InMemoryHost::new();
+        let mut program_context = ProgramContext::new(Rom::from_hex("3660023560046001600037").unwrap(), environment, &mut host);
+        program_context.run().unwrap();
+
+        // Reads past the end of calldata are zero padded
+        let mut loaded = [0u8; 32];
+        loaded[..2].copy_from_slice(&[0x33, 0x44]);
+        assert_eq!(u256::from_be_bytes(&loaded), program_context.stack.pop());
+        assert_eq!(u256::from_u8(4), program_context.stack.pop());
+        assert_eq!(vec![0x22, 0x33, 0x44, 0x00], program_context.memory.load(0, 4).unwrap());
+    }
+
+    #[test]
+    fn code() {
+        // CODESIZE PUSH1 0x08 PUSH1 0x02 PUSH1 0x00 CODECOPY, reading past the end of the code
+        let mut host = InMemoryHost::new();
+        let mut program_context = run("3860086002600039", &mut host);
+        assert_eq!(u256::from_u8(8), program_context.stack.pop());
+        assert_eq!(vec![0x08, 0x60, 0x02, 0x60, 0x00, 0x39, 0x00, 0x00], program_context.memory.load(0, 8).unwrap());
+    }
+
+    #[test]
+    fn ext_code() {
+        let mut host = InMemoryHost::new();
+        host.set_code(Address([0x01; 20]), vec![0xaa, 0xbb]);
+
+        // PUSH1 0x03 PUSH1 0x01 PUSH1 0x00 PUSH20 0x01.. EXTCODECOPY PUSH20 0x01.. EXTCODESIZE
+        let code = format!("600360016000{push}3c{push}3b", push = format!("73{}", "01".repeat(20)));
+        let mut program_context = run(&code, &mut host);
+        assert_eq!(u256::from_u8(2), program_context.stack.pop());
+        assert_eq!(vec![0xbb, 0x00, 0x00], program_context.memory.load(0, 3).unwrap());
+    }
+
+    #[test]
+    fn ext_code_hash() {
+        let mut host = InMemoryHost::new();
+        host.set_code(Address([0x01; 20]), vec![0xaa, 0xbb]);
+
+        // PUSH20 0x01.. EXTCODEHASH PUSH20 0x02.. EXTCODEHASH
+        let code = format!("73{}3f73{}3f", "01".repeat(20), "02".repeat(20));
+        let mut program_context = run(&code, &mut host);
+        assert_eq!(u256::zero(), program_context.stack.pop());
+        assert_eq!(u256::from_be_bytes(&crypto::keccak256(&[0xaa, 0xbb])), program_context.stack.pop());
+    }
+
+    #[test]
+    fn block() {
+        let header = Block {
+            parentHash: String::new(),
+            ommersHash: String::new(),
+            beneficiary: Address([0x01; 20]),
+            stateRoot: String::new(),
+            transactionRoot: String::new(),
+            receiptsRoot: String::new(),
+            logsBloom: 0,
+            difficulty: 0,
+            number: 1000,
+            gasLimit: 30_000_000,
+            gasUsed: 0,
+            timestamp: 1_700_000_000,
+            extraData: String::new(),
+            mixHash: H256([0x02; 32]),
+            nonce: 0,
+            baseFeePerGas: 7,
+        };
+
+        // COINBASE TIMESTAMP NUMBER PREVRANDAO GASLIMIT CHAINID BASEFEE
+        let mut host = InMemoryHost::new();
+        let mut program_context = ProgramContext::new(Rom::from_hex("41424344454648").unwrap(), Environment::default(), &mut host);
+        program_context.block = BlockEnv::from_header(&header, u256::one());
+        program_context.run().unwrap();
+        assert_eq!(u256::from_u8(7), program_context.stack.pop());
+        assert_eq!(u256::one(), program_context.stack.pop());
+        assert_eq!(u256::from_u128(30_000_000), program_context.stack.pop());
+        assert_eq!(H256([0x02; 32]).to_u256(), program_context.stack.pop());
+        assert_eq!(u256::from_u128(1000), program_context.stack.pop());
+        assert_eq!(u256::from_u128(1_700_000_000), program_context.stack.pop());
+        assert_eq!(Address([0x01; 20]).to_u256(), program_context.stack.pop());
+    }
+
+    #[test]
+    fn block_hash() {
+        let mut host = InMemoryHost::new();
+        for number in 0..1000u32 {
+            host.set_block_hash(u256::from_u128(number as u128), H256::from_u256(u256::from_u128(number as u128 + 1)));
+        }
+
+        // Only the 256 most recent ancestors are visible
+        // PUSH2 999 BLOCKHASH PUSH2 744 BLOCKHASH PUSH2 743 BLOCKHASH PUSH2 1000 BLOCKHASH
+        let mut program_context = ProgramContext::new(Rom::from_hex("6103e7406102e8406102e7406103e840").unwrap(), Environment::default(), &mut host);
+        program_context.block.number = u256::from_u128(1000);
+        program_context.run().unwrap();
+        assert_eq!(u256::zero(), program_context.stack.pop());
+        assert_eq!(u256::zero(), program_context.stack.pop());
+        assert_eq!(u256::from_u128(745), program_context.stack.pop());
+        assert_eq!(u256::from_u128(1000), program_context.stack.pop());
+    }
+
+    #[test]
+    fn storage() {
+        let address = Address([0x01; 20]);
+        let mut host = InMemoryHost::new();
+        host.set_balance(address, u256::from_u8(100));
+
+        // PUSH1 0x2a PUSH1 0x01 SSTORE PUSH1 0x01 SLOAD SELFBALANCE ADDRESS BALANCE
+        let environment = Environment { address, ..Environment::default() };
+        let mut program_context = ProgramContext::new(Rom::from_hex("602a600155600154473031").unwrap(), environment, &mut host);
+        program_context.run().unwrap();
+        assert_eq!(u256::from_u8(100), program_context.stack.pop());
+        assert_eq!(u256::from_u8(100), program_context.stack.pop());
+        assert_eq!(u256::from_u8(0x2a), program_context.stack.pop());
+        assert_eq!(u256::from_u8(0x2a), host.account(&address).unwrap().storage.get(&u256::one()));
+    }
+
+    fn bytecode(code: &str) -> Vec<u8> {
+        Rom::from_hex(code).unwrap().code().to_vec()
+    }
+
+    // PUSH20 address
+    fn push_address(address: &Address) -> String {
+        format!("73{}", encode_hex(&address.0))
+    }
+
+    #[test]
+    fn call() {
+        let caller = Address([0x01; 20]);
+        let callee = Address([0x02; 20]);
+        let mut host = InMemoryHost::new();
+        // CALLDATASIZE PUSH1 0x00 PUSH1 0x00 CALLDATACOPY CALLDATASIZE PUSH1 0x00 RETURN
+        host.set_code(callee, bytecode("366000600037366000f3"));
+
+        // Copy calldata to memory and pass it to the callee, then return what the callee returned
+        // CALLDATASIZE PUSH1 0x00 PUSH1 0x00 CALLDATACOPY
+        // PUSH1 0x04 PUSH1 0x20 CALLDATASIZE PUSH1 0x00 PUSH1 0x00 PUSH20 callee GAS CALL
+        // PUSH1 0x04 PUSH1 0x20 RETURN
+        let code = format!("366000600037600460203660006000{}5af160046020f3", push_address(&callee));
+        let environment = Environment { address: caller, calldata: vec![1, 2, 3, 4], ..Environment::default() };
+        let mut program_context = ProgramContext::new(Rom::from_hex(&code).unwrap(), environment, &mut host);
+        program_context.run().unwrap();
+        assert_eq!(vec![1, 2, 3, 4], program_context.output);
+        assert_eq!(u256::one(), program_context.stack.pop());
+    }
+
+    #[test]
+    fn call_precompile() {
+        // As the call test, with the identity precompile as the callee
+        let mut host = InMemoryHost::new();
+        let code = format!("366000600037600460203660006000{}5af160046020f3", push_address(&precompile_address(0x04)));
+        let environment = Environment { calldata: vec![1, 2, 3, 4], ..Environment::default() };
+        let mut program_context = ProgramContext::new(Rom::from_hex(&code).unwrap(), environment, &mut host);
+        program_context.run().unwrap();
+        assert_eq!(vec![1, 2, 3, 4], program_context.output);
+        assert_eq!(u256::one(), program_context.stack.pop());
+    }
+
+    #[test]
+    fn call_precompile_value() {
+        let caller = Address([0x01; 20]);
+        let (identity, sha256) = (precompile_address(0x04), precompile_address(0x02));
+        let mut host = InMemoryHost::new();
+        host.set_balance(caller, u256::from_u8(10));
+
+        // Value sent to a precompile is kept, unless the stipend doesn't cover hashing 0x2000 bytes
+        // PUSH1 0x00 PUSH1 0x00 PUSH1 0x00 PUSH1 0x00 PUSH1 0x03 PUSH20 identity GAS CALL
+        // PUSH1 0x00 PUSH1 0x00 PUSH2 0x2000 PUSH1 0x00 PUSH1 0x03 PUSH20 sha256 PUSH1 0x00 CALL
+        let code = format!("60006000600060006003{}5af16000600061200060006003{}6000f1", push_address(&identity), push_address(&sha256));
+        let environment = Environment { address: caller, ..Environment::default() };
+        let mut program_context = ProgramContext::new(Rom::from_hex(&code).unwrap(), environment, &mut host);
+        program_context.run().unwrap();
+        assert_eq!(u256::zero(), program_context.stack.pop());
+        assert_eq!(u256::one(), program_context.stack.pop());
+        assert_eq!(u256::from_u8(7), host.get_balance(&caller));
+        assert_eq!(u256::from_u8(3), host.get_balance(&identity));
+        assert_eq!(u256::zero(), host.get_balance(&sha256));
+    }
+
+    #[test]
+    fn return_data() {
+        let callee = Address([0x02; 20]);
+        let mut host = InMemoryHost::new();
+        // CALLDATASIZE PUSH1 0x00 PUSH1 0x00 CALLDATACOPY CALLDATASIZE PUSH1 0x00 RETURN
+        host.set_code(callee, bytecode("366000600037366000f3"));
+
+        // Echo calldata through the callee without a return region, then read it back from the buffer
+        // CALLDATASIZE PUSH1 0x00 PUSH1 0x00 CALLDATACOPY
+        // PUSH1 0x00 PUSH1 0x00 CALLDATASIZE PUSH1 0x00 PUSH1 0x00 PUSH20 callee GAS CALL RETURNDATASIZE
+        // PUSH1 0x03 PUSH1 offset PUSH1 0x00 RETURNDATACOPY PUSH1 0x03 PUSH1 0x00 RETURN
+        let code = |offset: &str| format!("366000600037600060003660006000{}5af13d6003{}60003e60036000f3", push_address(&callee), offset);
+        let environment = Environment { calldata: vec![1, 2, 3, 4], ..Environment::default() };
+        let mut program_context = ProgramContext::new(Rom::from_hex(&code("6001")).unwrap(), environment.clone(), &mut host);
+        program_context.run().unwrap();
+        assert_eq!(vec![2, 3, 4], program_context.output);
+        assert_eq!(u256::from_u8(4), program_context.stack.pop());
+        assert_eq!(u256::one(), program_context.stack.pop());
+
+        // EIP-211: reading past the end of the buffer halts
+        let mut program_context = ProgramContext::new(Rom::from_hex(&code("6002")).unwrap(), environment, &mut host);
+        assert!(matches!(program_context.run(), Err(ProgramError::ReturnDataOutOfBounds)));
+
+        // Even with nothing to copy. PUSH1 0x00 PUSH1 0x01 PUSH1 0x00 RETURNDATACOPY
+        let mut program_context = ProgramContext::new(Rom::from_hex("6000600160003e").unwrap(), Environment::default(), &mut host);
+        assert!(matches!(program_context.run(), Err(ProgramError::ReturnDataOutOfBounds)));
+    }
+
+    #[test]
+    fn log() {
+        let address = Address([0x01; 20]);
+        let mut host = InMemoryHost::new();
+        // CALLDATASIZE PUSH1 0x00 PUSH1 0x00 CALLDATACOPY PUSH1 0x02 PUSH1 0x01 CALLDATASIZE PUSH1 0x00 LOG2
+        let environment = Environment { address, calldata: vec![1, 2, 3], ..Environment::default() };
+        let mut program_context = ProgramContext::new(Rom::from_hex("36600060003760026001366000a2").unwrap(), environment, &mut host);
+        program_context.run().unwrap();
+        let topics = vec![H256::from_u256(u256::one()), H256::from_u256(u256::from_u8(2))];
+        let logs = vec![Log { address, topics, data: vec![1, 2, 3] }];
+        assert_eq!(logs, program_context.logs);
+        assert_eq!(logs, host.logs());
+
+        // PUSH1 0x00 PUSH1 0x00 LOG0
+        let environment = Environment { is_static: true, ..Environment::default() };
+        let mut program_context = ProgramContext::new(Rom::from_hex("60006000a0").unwrap(), environment, &mut host);
+        assert!(matches!(program_context.run(), Err(ProgramError::StaticStateChange)));
+        assert_eq!(logs, host.logs());
+    }
+
+    #[test]
+    fn call_logs() {
+        let logger = Address([0x02; 20]);
+        let reverter = Address([0x03; 20]);
+        let mut host = InMemoryHost::new();
+        // PUSH1 0x00 PUSH1 0x00 LOG0
+        host.set_code(logger, bytecode("60006000a0"));
+        // PUSH1 0x00 PUSH1 0x00 LOG0 PUSH1 0x00 PUSH1 0x00 REVERT
+        host.set_code(reverter, bytecode("60006000a060006000fd"));
+
+        // Only the logs of frames that succeed are kept
+        // PUSH1 0x00 PUSH1 0x00 PUSH1 0x00 PUSH1 0x00 PUSH1 0x00 PUSH20 address GAS CALL, for each address
+        let code = format!("6000600060006000600073{}5af16000600060006000600073{}5af1", encode_hex(&logger.0), encode_hex(&reverter.0));
+        let program_context = run(&code, &mut host);
+        let logs = vec![Log { address: logger, topics: Vec::new(), data: Vec::new() }];
+        assert_eq!(logs, program_context.logs);
+        assert_eq!(logs, host.logs());
+    }
+
+    #[test]
+    fn revert_state() {
+        let caller = Address([0x01; 20]);
+        let callee = Address([0x02; 20]);
+        let mut host = InMemoryHost::new();
+        host.set_balance(caller, u256::from_u8(10));
+        // PUSH1 0x01 PUSH1 0x00 SSTORE PUSH1 0x01 PUSH1 0x00 TSTORE PUSH1 0x00 PUSH1 0x00 REVERT
+        host.set_code(callee, bytecode("6001600055600160005d60006000fd"));
+
+        // The callee's storage writes and the value it was sent are undone, the caller's own write isn't
+        // PUSH1 0x01 PUSH1 0x00 SSTORE
+        // PUSH1 0x00 PUSH1 0x00 PUSH1 0x00 PUSH1 0x00 PUSH1 0x03 PUSH20 callee GAS CALL PUSH1 0x00 TLOAD
+        let code = format!("60016000556000600060006000600373{}5af160005c", encode_hex(&callee.0));
+        let environment = Environment { address: caller, ..Environment::default() };
+        let mut program_context = ProgramContext::new(Rom::from_hex(&code).unwrap(), environment.clone(), &mut host);
+        program_context.run().unwrap();
+        assert_eq!(u256::zero(), program_context.stack.pop()); // TLOAD
+        assert_eq!(u256::zero(), program_context.stack.pop()); // CALL
+        assert_eq!(u256::one(), host.sload(&caller, &u256::zero()));
+        assert_eq!(u256::zero(), host.sload(&callee, &u256::zero()));
+        assert_eq!(u256::from_u8(10), host.get_balance(&caller));
+
+        // A failed transaction undoes everything. PUSH1 0x02 PUSH1 0x00 SSTORE PUSH1 0x00 PUSH1 0x00 REVERT
+        let mut program_context = ProgramContext::new(Rom::from_hex("600260005560006000fd").unwrap(), environment, &mut host);
+        assert!(matches!(program_context.run(), Err(ProgramError::Reverted)));
+        assert_eq!(u256::one(), host.sload(&caller, &u256::zero()));
+    }
+
+    #[test]
+    fn call_value() {
+        let caller = Address([0x01; 20]);
+        let callee = Address([0x02; 20]);
+        let mut host = InMemoryHost::new();
+        host.set_balance(caller, u256::from_u8(10));
+
+        // Sends 3 then tries to send 30 more, which fails without halting
+        // PUSH1 0x00 PUSH1 0x00 PUSH1 0x00 PUSH1 0x00 PUSH1 value PUSH20 callee GAS CALL
+        let code = format!("60006000600060006003{callee}5af16000600060006000601e{callee}5af1", callee = push_address(&callee));
+        let environment = Environment { address: caller, ..Environment::default() };
+        let mut program_context = ProgramContext::new(Rom::from_hex(&code).unwrap(), environment, &mut host);
+        program_context.run().unwrap();
+        assert_eq!(u256::zero(), program_context.stack.pop());
+        assert_eq!(u256::one(), program_context.stack.pop());
+        assert_eq!(u256::from_u8(7), host.get_balance(&caller));
+        assert_eq!(u256::from_u8(3), host.get_balance(&callee));
+    }
+
+    #[test]
+    fn delegate_call() {
+        let caller = Address([0x01; 20]);
+        let library = Address([0x02; 20]);
+        let mut host = InMemoryHost::new();
+        // CALLER PUSH1 0x00 SSTORE CALLVALUE PUSH1 0x01 SSTORE
+        host.set_code(library, bytecode("3360005534600155"));
+
+        // PUSH1 0x00 PUSH1 0x00 PUSH1 0x00 PUSH1 0x00 PUSH20 library GAS DELEGATECALL
+        let code = format!("6000600060006000{}5af4", push_address(&library));
+        let environment = Environment {
+            address: caller,
+            caller: Address([0x03; 20]),
+            value: u256::from_u8(5),
+            ..Environment::default()
+        };
+        let mut program_context = ProgramContext::new(Rom::from_hex(&code).unwrap(), environment, &mut host);
+        program_context.run().unwrap();
+        assert_eq!(u256::one(), program_context.stack.pop());
+
+        // Writes land in the caller's storage, seeing the caller's own caller and value
+        assert_eq!(Address([0x03; 20]).to_u256(), host.sload(&caller, &u256::zero()));
+        assert_eq!(u256::from_u8(5), host.sload(&caller, &u256::one()));
+        assert_eq!(u256::zero(), host.sload(&library, &u256::zero()));
+    }
+
+    #[test]
+    fn static_call() {
+        let callee = Address([0x02; 20]);
+        let mut host = InMemoryHost::new();
+        // PUSH1 0x2a PUSH1 0x00 SSTORE
+        host.set_code(callee, bytecode("602a600055"));
+
+        // PUSH1 0x00 PUSH1 0x00 PUSH1 0x00 PUSH1 0x00 PUSH20 callee GAS STATICCALL
+        let code = format!("6000600060006000{}5afa", push_address(&callee));
+        let mut program_context = ProgramContext::new(Rom::from_hex(&code).unwrap(), Environment::default(), &mut host);
+        program_context.run().unwrap();
+        assert_eq!(u256::zero(), program_context.stack.pop());
+        assert_eq!(u256::zero(), host.sload(&callee, &u256::zero()));
+    }
+
+    #[test]
+    fn call_gas() {
+        let callee = Address([0x02; 20]);
+        let mut host = InMemoryHost::new();
+        // GAS PUSH1 0x00 SSTORE
+        host.set_code(callee, bytecode("5a600055"));
+
+        // Asks for all the gas, but a 64th is held back
+        // PUSH1 0x00 PUSH1 0x00 PUSH1 0x00 PUSH1 0x00 PUSH1 0x00 PUSH20 callee PUSH32 max CALL
+        let code = format!("60006000600060006000{}7f{}f1", push_address(&callee), "ff".repeat(32));
+        let mut program_context = ProgramContext::new(Rom::from_hex(&code).unwrap(), Environment::default(), &mut host);
+        program_context.gas = 6400;
+        program_context.run().unwrap();
+        assert_eq!(u256::one(), program_context.stack.pop());
+        assert_eq!(6400, program_context.gas);
+        assert_eq!(u256::from_u128(6300), host.sload(&callee, &u256::zero()));
+    }
+
+    #[test]
+    fn create() {
+        // Init code returning a single STOP as the deployed code
+        let init_code = "60016000f3";
+
+        // PUSH1 0x05 PUSH1 0x0f PUSH1 0x00 CODECOPY PUSH1 0x05 PUSH1 0x00 PUSH1 0x00 CREATE STOP init_code
+        let mut host = InMemoryHost::new();
+        let code = format!("6005600f600039600560006000f000{}", init_code);
+        let mut program_context = run(&code, &mut host);
+        let address = Address::from_create(&Address::zero(), 0);
+        assert_eq!(address.to_u256(), program_context.stack.pop());
+        assert_eq!(vec![0x00], host.get_code(&address));
+
+        // PUSH1 0x05 PUSH1 0x11 PUSH1 0x00 CODECOPY PUSH1 0x2a PUSH1 0x05 PUSH1 0x00 PUSH1 0x00 CREATE2 STOP init_code
+        let mut host = InMemoryHost::new();
+        let code = format!("60056011600039602a600560006000f500{}", init_code);
+        let mut program_context = run(&code, &mut host);
+        let address = Address::from_create2(&Address::zero(), &u256::from_u8(0x2a), &bytecode(init_code));
+        assert_eq!(address.to_u256(), program_context.stack.pop());
+        assert_eq!(vec![0x00], host.get_code(&address));
+
+        // PUSH1 0x00 PUSH1 0x00 PUSH1 0x00 CREATE
+        let environment = Environment { is_static: true, ..Environment::default() };
+        let mut host = InMemoryHost::new();
+        let mut program_context = ProgramContext::new(Rom::from_hex("600060006000f0").unwrap(), environment, &mut host);
+        assert!(matches!(program_context.run(), Err(ProgramError::StaticStateChange)));
+    }
+
+    #[test]
+    fn selfdestruct() {
+        let contract = Address([0x01; 20]);
+        let beneficiary = Address([0x02; 20]);
+        let destroy = |hard_fork| {
+            let mut host = InMemoryHost::new();
+            host.set_hard_fork(hard_fork);
+            // PUSH20 beneficiary SELFDESTRUCT
+            let code = format!("{}ff", push_address(&beneficiary));
+            host.set_code(contract, bytecode(&code));
+            host.set_balance(contract, u256::from_u8(10));
+            host.sstore(&contract, u256::one(), u256::one());
+            let environment = Environment { address: contract, ..Environment::default() };
+            ProgramContext::new(Rom::from_hex(&code).unwrap(), environment, &mut host).run().unwrap();
+            host
+        };
+
+        // Before Cancun the account is deleted at the end of the transaction
+        let host = destroy(HardFork::Shanghai);
+        assert!(host.account(&contract).is_none());
+        assert_eq!(u256::from_u8(10), host.get_balance(&beneficiary));
+
+        // EIP-6780: afterwards only the balance moves, code and storage persist
+        let host = destroy(HardFork::Cancun);
+        assert_eq!(u256::zero(), host.get_balance(&contract));
+        assert_eq!(u256::from_u8(10), host.get_balance(&beneficiary));
+        assert!(!host.get_code(&contract).is_empty());
+        assert_eq!(u256::one(), host.sload(&contract, &u256::one()));
+
+        // Unless the contract was created in the same transaction
+        // init code: PUSH1 0x01 PUSH1 0x01 SSTORE PUSH20 beneficiary SELFDESTRUCT
+        // PUSH1 0x1b PUSH1 0x0f PUSH1 0x00 CODECOPY PUSH1 0x1b PUSH1 0x00 PUSH1 0x00 CREATE STOP init_code
+        let code = format!("601b600f600039601b60006000f0006001600155{}ff", push_address(&beneficiary));
+        let mut host = InMemoryHost::new();
+        let mut program_context = run(&code, &mut host);
+        let created = Address::from_u256(program_context.stack.pop());
+        assert_eq!(Address::from_create(&Address::zero(), 0), created);
+        assert!(host.account(&created).is_none());
+        assert_eq!(u256::zero(), host.sload(&created, &u256::one()));
+    }
+
+    #[test]
+    fn call_depth() {
+        let mut host = InMemoryHost::new();
+        // PUSH1 0x00 PUSH1 0x00 PUSH1 0x00 PUSH1 0x00 PUSH1 0x00 PUSH1 0x00 GAS CALL
+        let environment = Environment { depth: CALL_DEPTH_LIMIT, ..Environment::default() };
+        let mut program_context = ProgramContext::new(Rom::from_hex("6000600060006000600060005af1").unwrap(), environment, &mut host);
+        program_context.run().unwrap();
+        assert_eq!(u256::zero(), program_context.stack.pop());
+    }
+
+    #[test]
+    fn blob_hash() {
+        let mut host = InMemoryHost::new();
+        let mut program_context = ProgramContext::new(Rom::from_hex("600049600149").unwrap(), Environment::default(), &mut host);
+        program_context.environment.blob_hashes = vec![u256::from_u8(0x01)];
+        program_context.run().unwrap();
+        assert_eq!(u256::zero(), program_context.stack.pop());
+        assert_eq!(u256::from_u8(0x01), program_context.stack.pop());
+    }
+}
@@ -0,0 +1,87 @@
+use std::cmp;
+
+use super::types::{ u256, Address };
+
+// A state mutation, holding what's needed to undo it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JournalEntry {
+    AccountCreated { address: Address },
+    BalanceChanged { address: Address, previous: u256 },
+    NonceChanged { address: Address, previous: u64 },
+    CodeChanged { address: Address, previous: Vec<u8> },
+    StorageChanged { address: Address, key: u256, previous: u256 },
+    TransientStorageChanged { address: Address, key: u256, previous: u256 },
+    ContractCreated { address: Address }, // Marked as created in the current transaction
+    Destructed { address: Address }, // Marked for deletion at the end of the transaction
+}
+
+// Position in the journal to revert back to
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Checkpoint(usize);
+
+// Every state mutation made during the transaction, in order. Frames take a checkpoint when they start and
+// revert to it if they fail, the transaction commits once it's over.
+#[derive(Debug, Default)]
+pub struct Journal {
+    entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    pub fn new() -> Journal {
+        Journal::default()
+    }
+
+    pub fn record(&mut self, entry: JournalEntry) {
+        self.entries.push(entry);
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.entries.len())
+    }
+
+    // Removes the entries made since the checkpoint, most recent first so they can be undone in order
+    pub fn revert_to(&mut self, checkpoint: Checkpoint) -> Vec<JournalEntry> {
+        let mut entries = self.entries.split_off(cmp::min(checkpoint.0, self.entries.len()));
+        entries.reverse();
+        entries
+    }
+
+    // Makes every change permanent, invalidating any outstanding checkpoints
+    pub fn commit(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn revert_to() {
+        let mut journal = Journal::new();
+        journal.record(JournalEntry::AccountCreated { address: Address([0x01; 20]) });
+        let checkpoint = journal.checkpoint();
+        journal.record(JournalEntry::NonceChanged { address: Address([0x01; 20]), previous: 0 });
+        journal.record(JournalEntry::NonceChanged { address: Address([0x01; 20]), previous: 1 });
+
+        let undone = journal.revert_to(checkpoint);
+        assert_eq!(vec![
+            JournalEntry::NonceChanged { address: Address([0x01; 20]), previous: 1 },
+            JournalEntry::NonceChanged { address: Address([0x01; 20]), previous: 0 },
+        ], undone);
+        assert_eq!(1, journal.len());
+
+        journal.commit();
+        assert!(journal.is_empty());
+        assert!(journal.revert_to(checkpoint).is_empty());
+    }
+}
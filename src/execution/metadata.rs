@@ -0,0 +1,165 @@
+use std::fmt;
+
+use super::program_context::encode_hex;
+
+// Solidity appends CBOR encoded metadata to the runtime code, followed by its length as two big endian
+// bytes. It's a map with text keys, e.g. { "ipfs": bytes, "solc": bytes } where the compiler version is
+// three bytes for releases and a string for nightly builds.
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    pub offset: usize, // Start of the CBOR, the metadata runs to the end of the code
+    pub ipfs: Option<Vec<u8>>, // Multihash of the metadata JSON
+    pub bzzr0: Option<Vec<u8>>, // Swarm hashes, used by older compilers
+    pub bzzr1: Option<Vec<u8>>,
+    pub solc: Option<String>,
+    pub experimental: bool,
+}
+
+enum Value {
+    Bytes(Vec<u8>),
+    Text(String),
+    Bool(bool),
+}
+
+impl Metadata {
+    // None unless the code ends with a well formed metadata map
+    pub fn from_code(code: &[u8]) -> Option<Metadata> {
+        let length_offset = code.len().checked_sub(2)?;
+        let length = u16::from_be_bytes([code[length_offset], code[length_offset + 1]]) as usize;
+        let offset = length_offset.checked_sub(length)?;
+
+        let mut reader = Reader { data: &code[offset..length_offset], position: 0 };
+        let (major, entries) = reader.header()?;
+        if major != 5 || entries == 0 {
+            return None;
+        }
+        let mut metadata = Metadata { offset, ..Metadata::default() };
+        for _ in 0..entries {
+            let key = match reader.value()? {
+                Value::Text(key) => key,
+                _ => return None,
+            };
+            match (key.as_str(), reader.value()?) {
+                ("ipfs", Value::Bytes(hash)) => metadata.ipfs = Some(hash),
+                ("bzzr0", Value::Bytes(hash)) => metadata.bzzr0 = Some(hash),
+                ("bzzr1", Value::Bytes(hash)) => metadata.bzzr1 = Some(hash),
+                ("solc", Value::Bytes(version)) if version.len() == 3 => {
+                    metadata.solc = Some(format!("{}.{}.{}", version[0], version[1], version[2]));
+                },
+                ("solc", Value::Text(version)) => metadata.solc = Some(version),
+                ("experimental", Value::Bool(experimental)) => metadata.experimental = experimental,
+                _ => {}, // Fields from future compilers
+            }
+        }
+
+        // The length must account for exactly the map
+        if reader.position != reader.data.len() {
+            return None;
+        }
+        Some(metadata)
+    }
+
+    // Decoded fields that are present, as names and display values
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = Vec::new();
+        let hashes = [("ipfs", &self.ipfs), ("bzzr0", &self.bzzr0), ("bzzr1", &self.bzzr1)];
+        for (name, hash) in hashes {
+            if let Some(hash) = hash {
+                fields.push((name, format!("0x{}", encode_hex(hash))));
+            }
+        }
+        if let Some(solc) = &self.solc {
+            fields.push(("solc", solc.clone()));
+        }
+        if self.experimental {
+            fields.push(("experimental", String::from("true")));
+        }
+        fields
+    }
+}
+
+// ipfs 0x1220..., solc 0.8.24
+impl fmt::Display for Metadata {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fields: Vec<String> = self.fields().iter().map(|(name, value)| format!("{} {}", name, value)).collect();
+        write!(f, "{}", fields.join(", "))
+    }
+}
+
+// Just enough of CBOR (RFC 8949) for the metadata
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, size: usize) -> Option<&[u8]> {
+        let bytes = self.data.get(self.position..self.position.checked_add(size)?)?;
+        self.position += size;
+        Some(bytes)
+    }
+
+    // Major type and the argument following it, a length for strings and maps
+    fn header(&mut self) -> Option<(u8, usize)> {
+        let initial = self.take(1)?[0];
+        let (major, info) = (initial >> 5, initial & 0x1f);
+        let argument = match info {
+            0..=23 => info as u64,
+            24 => self.take(1)?[0] as u64,
+            25 => u16::from_be_bytes(self.take(2)?.try_into().ok()?) as u64,
+            26 => u32::from_be_bytes(self.take(4)?.try_into().ok()?) as u64,
+            27 => u64::from_be_bytes(self.take(8)?.try_into().ok()?),
+            _ => return None, // Indefinite lengths aren't used
+        };
+        Some((major, usize::try_from(argument).ok()?))
+    }
+
+    fn value(&mut self) -> Option<Value> {
+        match self.header()? {
+            (2, length) => Some(Value::Bytes(self.take(length)?.to_vec())),
+            (3, length) => Some(Value::Text(String::from_utf8(self.take(length)?.to_vec()).ok()?)),
+            (7, 20) => Some(Value::Bool(false)),
+            (7, 21) => Some(Value::Bool(true)),
+            _ => None,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // { "ipfs": 0x1220 ++ 32 bytes, "solc": 0x000818 } as emitted by solc 0.8.24
+    fn solc_metadata() -> Vec<u8> {
+        let mut metadata = vec![0xa2, 0x64, b'i', b'p', b'f', b's', 0x58, 0x22, 0x12, 0x20];
+        metadata.extend_from_slice(&[0xab; 32]);
+        metadata.extend_from_slice(&[0x64, b's', b'o', b'l', b'c', 0x43, 0x00, 0x08, 0x18, 0x00, 0x33]);
+        metadata
+    }
+
+    #[test]
+    fn from_code() {
+        let code = [vec![0x60, 0x80, 0x00], solc_metadata()].concat();
+        let metadata = Metadata::from_code(&code).unwrap();
+        assert_eq!(3, metadata.offset);
+        assert_eq!(Some(String::from("0.8.24")), metadata.solc);
+        assert_eq!(34, metadata.ipfs.unwrap().len());
+        assert!(metadata.bzzr0.is_none());
+
+        // A nightly build's version string
+        let code = [0xa1, 0x64, b's', b'o', b'l', b'c', 0x65, b'0', b'.', b'8', b'-', b'n', 0x00, 0x0c];
+        assert_eq!(Some(String::from("0.8-n")), Metadata::from_code(&code).unwrap().solc);
+    }
+
+    #[test]
+    fn not_metadata() {
+        assert!(Metadata::from_code(&[]).is_none());
+        assert!(Metadata::from_code(&[0x60, 0x01, 0x00, 0x05]).is_none());
+        // Truncated, as when constructor arguments follow
+        let mut code = solc_metadata();
+        code.push(0x00);
+        assert!(Metadata::from_code(&code).is_none());
+    }
+}
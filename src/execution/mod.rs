@@ -1,4 +1,18 @@
-
+pub mod assembler;
+pub mod cfg;
+pub mod diff;
+pub mod disassembler;
+pub mod environment;
+pub mod host;
+#[allow(non_upper_case_globals)] // Instructions is a lookup table, named like one
 pub mod instructions;
+pub mod journal;
+pub mod metadata;
+#[allow(non_upper_case_globals)] // As is Precompiles
+pub mod precompiles;
 pub mod program_context;
+pub mod selectors;
+pub mod split;
+pub mod stack_analysis;
+pub mod stats;
 pub mod types;
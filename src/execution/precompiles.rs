@@ -0,0 +1,206 @@
+use std::cmp;
+use std::collections::HashMap;
+
+use crate::crypto;
+use super::host::CallResult;
+use super::program_context::MEMORY_LIMIT;
+use super::types::{ u256, Address };
+
+// Appendix E. Precompiled Contracts.
+//
+// Native implementations at the addresses 0x01 to 0x0a, run in place of EVM code whatever the kind of call.
+// So far only identity, sha256, ripemd160 and modexp exist, calls to the others behave like calls to an
+// account with no code.
+pub struct Precompile {
+    pub name: &'static str,
+    pub gas_cost: fn(input: &[u8]) -> u64,
+    pub execute: fn(input: &[u8]) -> Option<Vec<u8>>, // None fails the call
+}
+
+impl Precompile {
+    // Fails, consuming all the gas, if the gas given doesn't cover the cost or the input is rejected
+    pub fn call(&self, input: &[u8], gas: u64) -> CallResult {
+        let cost = (self.gas_cost)(input);
+        let output = if cost > gas { None } else { (self.execute)(input) };
+        match output {
+            Some(output) => CallResult { success: true, output, gas_left: gas - cost, created_address: None, logs: Vec::new() },
+            None => CallResult { success: false, output: Vec::new(), gas_left: 0, created_address: None, logs: Vec::new() },
+        }
+    }
+}
+
+pub fn precompile_address(index: u8) -> Address {
+    Address::from_u256(u256::from_u8(index))
+}
+
+lazy_static! {
+    pub static ref Precompiles: HashMap<Address, Precompile> = HashMap::from([
+        (precompile_address(0x02), Precompile { name: "SHA256", gas_cost: sha256_gas, execute: sha256 }),
+        (precompile_address(0x03), Precompile { name: "RIPEMD160", gas_cost: ripemd160_gas, execute: ripemd160 }),
+        (precompile_address(0x04), Precompile { name: "IDENTITY", gas_cost: identity_gas, execute: identity }),
+        (precompile_address(0x05), Precompile { name: "MODEXP", gas_cost: modexp_gas, execute: modexp }),
+    ]);
+}
+
+// Number of 32 byte words, rounding up
+fn words(input: &[u8]) -> u64 {
+    input.len().div_ceil(32) as u64
+}
+
+// 0x02: SHA256
+fn sha256_gas(input: &[u8]) -> u64 {
+    60 + 12 * words(input)
+}
+
+fn sha256(input: &[u8]) -> Option<Vec<u8>> {
+    Some(crypto::sha256(input).to_vec())
+}
+
+// 0x03: RIPEMD160, left padded to 32 bytes
+fn ripemd160_gas(input: &[u8]) -> u64 {
+    600 + 120 * words(input)
+}
+
+fn ripemd160(input: &[u8]) -> Option<Vec<u8>> {
+    let mut output = vec![0u8; 12];
+    output.extend_from_slice(&crypto::ripemd160(input));
+    Some(output)
+}
+
+// 0x04: IDENTITY
+fn identity_gas(input: &[u8]) -> u64 {
+    15 + 3 * words(input)
+}
+
+fn identity(input: &[u8]) -> Option<Vec<u8>> {
+    Some(input.to_vec())
+}
+
+// 0x05: MODEXP (EIP-198). The input is the lengths of the base, exponent and modulus as 32 byte words,
+// followed by the numbers themselves, with any missing bytes treated as zero.
+fn modexp_lengths(input: &[u8]) -> Option<(usize, usize, usize)> {
+    let length = |index: usize| u256::from_be_bytes(&padded(input, 32 * index, 32)).to_usize();
+    Some((length(0)?, length(1)?, length(2)?))
+}
+
+// EIP-2565
+fn modexp_gas(input: &[u8]) -> u64 {
+    let Some((base_length, exponent_length, modulus_length)) = modexp_lengths(input) else {
+        return u64::MAX;
+    };
+    let words = (cmp::max(base_length, modulus_length) as u128).div_ceil(8);
+    let multiplication_complexity = words.saturating_mul(words);
+
+    // Roughly the number of squarings, from the bit length of the exponent's first 32 bytes
+    let head = padded(input, 96usize.saturating_add(base_length), cmp::min(exponent_length, 32));
+    let head_bits = head.iter().position(|&byte| byte != 0)
+        .map(|i| 8 * (head.len() - i) as u128 - head[i].leading_zeros() as u128)
+        .unwrap_or(0);
+    let iterations = if exponent_length <= 32 {
+        head_bits.saturating_sub(1)
+    } else {
+        (8 * (exponent_length as u128 - 32)).saturating_add(head_bits.saturating_sub(1))
+    };
+
+    let gas = multiplication_complexity.saturating_mul(cmp::max(iterations, 1)) / 3;
+    cmp::max(200, cmp::min(gas, u64::MAX as u128) as u64)
+}
+
+// The gas cost is clamped, so with enough gas it doesn't stop the numbers being allocated. Like memory, each
+// is capped instead, and longer ones fail the call.
+fn modexp(input: &[u8]) -> Option<Vec<u8>> {
+    let (base_length, exponent_length, modulus_length) = modexp_lengths(input)?;
+    if modulus_length == 0 {
+        return Some(Vec::new());
+    }
+    if cmp::max(base_length, cmp::max(exponent_length, modulus_length)) > MEMORY_LIMIT {
+        return None;
+    }
+    let exponent_offset = 96usize.checked_add(base_length)?;
+    let modulus_offset = exponent_offset.checked_add(exponent_length)?;
+    let base = padded(input, 96, base_length);
+    let exponent = padded(input, exponent_offset, exponent_length);
+    let modulus = padded(input, modulus_offset, modulus_length);
+    Some(crypto::modexp(&base, &exponent, &modulus))
+}
+
+// size bytes of the input starting at the offset, zero padded past its end
+fn padded(input: &[u8], offset: usize, size: usize) -> Vec<u8> {
+    let mut data = vec![0u8; size];
+    if offset < input.len() {
+        let available = cmp::min(size, input.len() - offset);
+        data[..available].copy_from_slice(&input[offset..offset + available]);
+    }
+    data
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::program_context::encode_hex;
+
+    fn call(index: u8, input: &[u8], gas: u64) -> CallResult {
+        Precompiles.get(&precompile_address(index)).unwrap().call(input, gas)
+    }
+
+    fn from_hex(s: &str) -> Vec<u8> {
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn hashes() {
+        let result = call(0x02, b"", 100);
+        assert_eq!("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855", encode_hex(&result.output));
+        assert_eq!(40, result.gas_left);
+
+        let result = call(0x03, b"abc", 1000);
+        assert_eq!("0000000000000000000000008eb208f7e05d987a9b044a8e98c6b087f15a0bfc", encode_hex(&result.output));
+        assert_eq!(280, result.gas_left);
+    }
+
+    #[test]
+    fn identity() {
+        let result = call(0x04, &[1, 2, 3], 100);
+        assert!(result.success);
+        assert_eq!(vec![1, 2, 3], result.output);
+        assert_eq!(82, result.gas_left);
+
+        // Not enough gas consumes it all
+        let result = call(0x04, &[1, 2, 3], 17);
+        assert!(!result.success);
+        assert_eq!(0, result.gas_left);
+    }
+
+    #[test]
+    fn modexp() {
+        // From EIP-198, 3^(p - 1) mod p = 1, costing 1360 gas under EIP-2565
+        let input = from_hex(&format!("{}{}{}03{}{}",
+            "0000000000000000000000000000000000000000000000000000000000000001",
+            "0000000000000000000000000000000000000000000000000000000000000020",
+            "0000000000000000000000000000000000000000000000000000000000000020",
+            "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2e",
+            "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f"));
+        let result = call(0x05, &input, 10_000);
+        assert_eq!("0000000000000000000000000000000000000000000000000000000000000001", encode_hex(&result.output));
+        assert_eq!(10_000 - 1360, result.gas_left);
+
+        // Also from EIP-198, a base of zero with the input cut short
+        let input = from_hex(&format!("{}{}{}{}",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "0000000000000000000000000000000000000000000000000000000000000020",
+            "0000000000000000000000000000000000000000000000000000000000000020",
+            "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2e"));
+        let result = call(0x05, &input, 10_000);
+        assert_eq!(vec![0u8; 32], result.output);
+
+        // Lengths too large to ever pay for
+        let input = from_hex(&format!("{}{}{}", "ff".repeat(32), "00".repeat(32), "00".repeat(32)));
+        assert!(!call(0x05, &input, u64::MAX - 1).success);
+        // Or to allocate, even with unlimited gas
+        let modulus_length = format!("{:064x}", 1);
+        assert!(!call(0x05, &from_hex(&format!("{}{}{}", "ff".repeat(32), "00".repeat(32), modulus_length)), u64::MAX).success);
+        let input = from_hex(&format!("{:064x}{}{}", 1u64 << 40, "00".repeat(32), modulus_length));
+        assert!(!call(0x05, &input, u64::MAX).success);
+    }
+}
@@ -1,43 +1,168 @@
 
+use std::collections::HashMap;
+
+use super::disassembler::{ self, DisassembledInstruction };
+use super::environment::{ BlockEnv, Environment };
+use super::host::{ CallResult, Host, Log, Message };
 use super::instructions::Instructions;
+use super::precompiles::Precompiles;
 use super::types::u256;
 
 #[derive(Debug)]
 pub enum ProgramError {
     Stopped,
     ROMOutOfBoundsError(ROMOutOfBoundsError),
+    Returned,
+    Reverted,
+    InvalidOpCode(u8),
+    MemoryOutOfBounds,
+    StaticStateChange,
+    ReturnDataOutOfBounds,
 }
 
 impl fmt::Display for ProgramError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match &*self {
+        match self {
             ProgramError::Stopped => write!(f, "Recieved STOP opcode"),
             ProgramError::ROMOutOfBoundsError(err) => write!(f, "{}", err),
+            ProgramError::Returned => write!(f, "Recieved RETURN opcode"),
+            ProgramError::Reverted => write!(f, "Execution reverted"),
+            ProgramError::InvalidOpCode(opcode) => write!(f, "Invalid opcode {:#04x}", opcode),
+            ProgramError::MemoryOutOfBounds => write!(f, "Memory access out of bounds"),
+            ProgramError::StaticStateChange => write!(f, "State modification attempted in a static call"),
+            ProgramError::ReturnDataOutOfBounds => write!(f, "Return data access out of bounds"),
         }
     }
 }
 
-pub struct ProgramContext {
+// No gas is charged for opcodes yet, this is only what a frame can forward to the calls it makes
+pub const DEFAULT_GAS: u64 = 30_000_000;
+
+pub struct ProgramContext<'a> {
     pub rom: Rom,
     pub stack: Stack,
     pub memory: Memory,
-    pub storage: Storage,
+    pub environment: Environment,
+    pub block: BlockEnv,
+    pub host: &'a mut dyn Host,
+    pub gas: u64,
+    pub output: Vec<u8>, // Set by RETURN and REVERT
+    pub return_data: Vec<u8>, // Output of the most recent call or create made by this frame
+    pub logs: Vec<Log>, // Emitted by this frame and the calls it made, kept only if the frame succeeds
 }
 
-impl ProgramContext {
-    pub fn new(rom: Rom) -> ProgramContext {
-        ProgramContext { rom, stack: Stack::new(), memory: Memory::new(), storage: Storage::new() }
+impl<'a> ProgramContext<'a> {
+    pub fn new(rom: Rom, environment: Environment, host: &'a mut dyn Host) -> ProgramContext<'a> {
+        ProgramContext {
+            rom,
+            stack: Stack::new(),
+            memory: Memory::new(),
+            environment,
+            block: BlockEnv::default(),
+            host,
+            gas: DEFAULT_GAS,
+            output: Vec::new(),
+            return_data: Vec::new(),
+            logs: Vec::new(),
+        }
+    }
+
+    pub fn step(&mut self) -> Result<(), ProgramError> {
+        let opcode = self.rom.next_byte()?;
+        match Instructions.get(&opcode) {
+            Some(instruction) => instruction.execute(self),
+            None => Err(ProgramError::InvalidOpCode(opcode)),
+        }
+    }
+
+    // Runs this call frame until a halting opcode or the end of the ROM, which is treated as STOP. Anything
+    // other than a normal halt is an error, with REVERT's data left in output.
+    pub fn execute(&mut self) -> Result<(), ProgramError> {
+        let result = loop {
+            if let Err(err) = self.step() {
+                break err;
+            }
+        };
+        match result {
+            ProgramError::Stopped | ProgramError::Returned | ProgramError::ROMOutOfBoundsError(_) => Ok(()),
+            err => Err(err),
+        }
+    }
+
+    // Runs code in a new call frame for the message. This is the interpreter's half of a message call,
+    // hosts are expected to have handled any value transfer.
+    pub fn execute_message(host: &'a mut dyn Host, message: Message, code: Vec<u8>) -> CallResult {
+        let mut program_context = ProgramContext::new(Rom::new(code), message.environment, host);
+        program_context.block = message.block;
+        program_context.gas = message.gas;
+        let result = program_context.execute();
+        let gas_left = match result {
+            Ok(()) | Err(ProgramError::Reverted) => program_context.gas,
+            Err(_) => 0, // Exceptional halts consume all the frame's gas
+        };
+        let logs = if result.is_ok() { program_context.logs } else { Vec::new() };
+        CallResult { success: result.is_ok(), output: program_context.output, gas_left, created_address: None, logs }
+    }
+
+    // Makes a message call from this frame. Precompiles are run here rather than by the host, which only
+    // sees a call to an account without code, so it still transfers the value and journals the call.
+    pub fn call(&mut self, message: Message) -> CallResult {
+        let precompile = match Precompiles.get(&message.code_address) {
+            Some(precompile) => precompile,
+            None => return self.host.call(message),
+        };
+        let (input, gas) = (message.environment.calldata.clone(), message.gas);
+        let checkpoint = self.host.checkpoint();
+        let transfer = self.host.call(message);
+        if !transfer.success {
+            return transfer;
+        }
+        let result = precompile.call(&input, gas);
+        if !result.success {
+            self.host.revert_to(checkpoint);
+        }
+        result
+    }
+
+    // Executes the program as a whole transaction, letting the host finalise it afterwards
+    pub fn run(&mut self) -> Result<(), ProgramError> {
+        let checkpoint = self.host.checkpoint();
+        let result = self.execute();
+        if result.is_ok() {
+            for log in &self.logs {
+                self.host.emit_log(log.clone());
+            }
+        } else {
+            self.logs.clear();
+            self.host.revert_to(checkpoint);
+        }
+        self.host.end_transaction();
+        result
     }
 }
 
 // UTILS START
-use std::{fmt::Write, num::ParseIntError};
+use std::fmt::Write;
+use std::io;
+
+// Accepts an optional 0x prefix and ignores whitespace, so files wrapped over several lines load as one
+fn decode_hex(s: &str) -> Result<Vec<u8>, LoadError> {
+    let trimmed = s.trim_start();
+    let prefix = if trimmed.starts_with("0x") || trimmed.starts_with("0X") { 2 } else { 0 };
+    let start = s.len() - trimmed.len() + prefix;
 
-fn decode_hex(s: &str) -> Result<Vec<u8>, ParseIntError> {
-    (0..s.len())
-        .step_by(2)
-        .map(|i| u8::from_str_radix(&s[i..i + 2], 16))
-        .collect()
+    let mut digits: Vec<u8> = Vec::with_capacity(s.len());
+    for (index, character) in s[start..].char_indices() {
+        match character.to_digit(16) {
+            Some(digit) => digits.push(digit as u8),
+            None if character.is_whitespace() => {},
+            None => return Err(LoadError::InvalidHexCharacter { character, index: start + index }),
+        }
+    }
+    if !digits.len().is_multiple_of(2) {
+        return Err(LoadError::OddLength(digits.len()));
+    }
+    Ok(digits.chunks_exact(2).map(|pair| (pair[0] << 4) | pair[1]).collect())
 }
 
 pub fn encode_hex(bytes: &[u8]) -> String {
@@ -65,6 +190,29 @@ impl fmt::Display for ROMOutOfBoundsError {
     }
 }
 
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    OddLength(usize), // Number of hex digits
+    InvalidHexCharacter { character: char, index: usize },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io(err) => write!(f, "{}", err),
+            LoadError::OddLength(digits) => write!(f, "Odd number of hex digits ({}), bytecode must be whole bytes", digits),
+            LoadError::InvalidHexCharacter { character, index } => write!(f, "Invalid hex character {:?} at position {}", character, index),
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(err: io::Error) -> Self {
+        LoadError::Io(err)
+    }
+}
+
 pub struct Rom {
     rom: Vec<u8>,
     pc: u128, // Not sure what size this should be, I guess it could be infinite...
@@ -77,10 +225,12 @@ impl Rom {
         Rom { rom, pc: 0, size }
     }
 
-    pub fn from_string(s: &str) -> Rom {
-        let rom = decode_hex(s).unwrap();
-        let size: usize = rom.len();
-        Rom { rom, pc: 0, size }
+    pub fn from_hex(s: &str) -> Result<Rom, LoadError> {
+        Ok(Rom::new(decode_hex(s)?))
+    }
+
+    pub fn code(&self) -> &[u8] {
+        &self.rom
     }
 
     pub fn next_byte(&mut self) -> Result<u8, ProgramError> {
@@ -89,26 +239,12 @@ impl Rom {
             self.pc += 1;
             return Ok(self.rom[pc]);
         }
-        Err(ProgramError::ROMOutOfBoundsError(ROMOutOfBoundsError { index: pc, max_rom_index: self.size - 1 }))
-    }
-
-    pub fn disassemble(&mut self) -> Result<(), ProgramError> {
-        loop {
-            let mut line: String = String::new();
-            let opcode = &self.next_byte()?;
-            if let Some(instruction) = &Instructions.get(&opcode) {
-                line.push_str(format!("  {:6}", instruction.mnemonic).as_str());
-                let mut rom_args = instruction.rom_items_used;
-                while rom_args > 0 {
-                    line.push_str(format!("  {:#04x}", self.next_byte()?).as_str());
-                    rom_args -= 1;
-                }
-            } else {
-                line.push_str(format!("  {:#04x}", opcode).as_str());
-            }
-            println!("{}", line);
-        }
-        Ok(())
+        Err(ProgramError::ROMOutOfBoundsError(ROMOutOfBoundsError { index: pc, max_rom_index: self.size.saturating_sub(1) }))
+    }
+
+    // The whole ROM, regardless of the program counter
+    pub fn disassemble(&self) -> Vec<DisassembledInstruction> {
+        disassembler::disassemble(&self.rom)
     }
 }
 
@@ -135,7 +271,7 @@ impl Stack {
     }
 
     pub fn pop(&mut self) -> u256 {
-        if self.stack.len() < 1 {
+        if self.stack.is_empty() {
             // Next item will results in 0 to -1
             println!("TODO: Implement stack underflow");
         }
@@ -143,6 +279,19 @@ impl Stack {
     }
 }
 
+impl Default for Stack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// STACK END
+
+// MEMORY START
+
+// There's no gas metering to bound memory expansion yet, so cap it instead
+pub const MEMORY_LIMIT: usize = 1 << 25;
+
 pub struct Memory {
     memory: Vec<u8>,
 }
@@ -151,15 +300,120 @@ impl Memory {
     pub fn new() -> Memory {
         Memory { memory: Vec::new() }
     }
+
+    pub fn size(&self) -> usize {
+        self.memory.len()
+    }
+
+    // Memory grows in 32 byte words to cover any accessed range. Zero length accesses never expand it.
+    pub fn expand(&mut self, offset: usize, size: usize) -> Result<(), ProgramError> {
+        if size == 0 {
+            return Ok(());
+        }
+        let end = offset.checked_add(size).ok_or(ProgramError::MemoryOutOfBounds)?;
+        if end > MEMORY_LIMIT {
+            return Err(ProgramError::MemoryOutOfBounds);
+        }
+        let words = end.div_ceil(32);
+        if words * 32 > self.memory.len() {
+            self.memory.resize(words * 32, 0);
+        }
+        Ok(())
+    }
+
+    pub fn load(&mut self, offset: usize, size: usize) -> Result<Vec<u8>, ProgramError> {
+        if size == 0 {
+            return Ok(Vec::new());
+        }
+        self.expand(offset, size)?;
+        Ok(self.memory[offset..offset + size].to_vec())
+    }
+
+    pub fn store(&mut self, offset: usize, data: &[u8]) -> Result<(), ProgramError> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        self.expand(offset, data.len())?;
+        self.memory[offset..offset + data.len()].copy_from_slice(data);
+        Ok(())
+    }
+
+    // Overlapping ranges behave as if copied via an intermediate buffer
+    pub fn copy(&mut self, destination: usize, source: usize, size: usize) -> Result<(), ProgramError> {
+        if size == 0 {
+            return Ok(());
+        }
+        self.expand(source, size)?;
+        self.expand(destination, size)?;
+        self.memory.copy_within(source..source + size, destination);
+        Ok(())
+    }
 }
 
-// TODO: Change to u256
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// MEMORY END
+
+// STORAGE START
+
+// Unset keys read as zero
 pub struct Storage {
-    storage: Vec<u128>,
+    storage: HashMap<u256, u256>,
 }
 
 impl Storage {
     pub fn new() -> Storage {
-        Storage { storage: Vec::new() }
+        Storage { storage: HashMap::new() }
+    }
+
+    pub fn get(&self, key: &u256) -> u256 {
+        self.storage.get(key).copied().unwrap_or_default()
+    }
+
+    pub fn set(&mut self, key: u256, value: u256) {
+        if value.is_zero() {
+            self.storage.remove(&key);
+        } else {
+            self.storage.insert(key, value);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.storage.clear();
+    }
+}
+
+impl Default for Storage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// STORAGE END
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_hex() {
+        assert_eq!(&[0x60, 0x01], Rom::from_hex("6001").unwrap().code());
+        assert_eq!(&[0x60, 0x01, 0x00], Rom::from_hex("  0x6001\n00\n").unwrap().code());
+        assert_eq!(&[0xab], Rom::from_hex("0XaB").unwrap().code());
+        assert!(Rom::from_hex("").unwrap().code().is_empty());
+    }
+
+    #[test]
+    fn from_hex_errors() {
+        assert!(matches!(Rom::from_hex("600"), Err(LoadError::OddLength(3))));
+        assert!(matches!(Rom::from_hex("0x60g1"), Err(LoadError::InvalidHexCharacter { character: 'g', index: 4 })));
+        // Only a leading prefix is accepted
+        assert!(matches!(Rom::from_hex("600x"), Err(LoadError::InvalidHexCharacter { character: 'x', index: 3 })));
+        assert!(matches!(Rom::from_hex("60é1"), Err(LoadError::InvalidHexCharacter { character: 'é', index: 2 })));
     }
 }
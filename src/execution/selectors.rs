@@ -0,0 +1,146 @@
+use std::collections::HashSet;
+
+use super::cfg::{ BasicBlock, ControlFlowGraph, Exit, Target };
+use super::disassembler::{ DisassembledInstruction, Disassembly };
+use super::instructions::OpCode;
+
+// An external function found in a dispatcher: the first four bytes of the calldata it's called with, and
+// where its code starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selector {
+    pub selector: [u8; 4],
+    pub entry: usize,
+}
+
+// Solidity and Vyper compare the calldata's selector against each function in turn, jumping to the function
+// on a match:
+//
+//     PUSH4 selector [DUPn] EQ PUSH2 entry JUMPI
+//
+// The DUP appears when the selector is pushed before the one it's compared with is copied into place. Leading
+// zero bytes of a selector are dropped by the optimizer, leaving a shorter PUSH. Only the dispatcher is
+// searched: the blocks from where the selector is read with CALLDATALOAD PUSH1 0xe0 SHR, through the failed
+// comparisons and the jumps of any binary search over them, but not into the functions. The result is in
+// code order, keeping only the first entry for a selector that's compared more than once.
+pub fn find_selectors(disassembly: &Disassembly) -> Vec<Selector> {
+    let cfg = ControlFlowGraph::new(disassembly);
+    let mut pending: Vec<usize> = cfg.blocks.iter().filter(|block| reads_selector(block)).map(|block| block.start).collect();
+    let mut visited = HashSet::new();
+    let mut found = Vec::new();
+    while let Some(start) = pending.pop() {
+        let Some(block) = cfg.block(start).filter(|_| visited.insert(start)) else {
+            continue;
+        };
+        match (comparison(block), block.exit) {
+            (Some(selector), Exit::JumpI { target: Target::Static(entry), fall_through }) => {
+                found.push((block.start, Selector { selector, entry }));
+                pending.push(fall_through);
+            },
+            (Some(selector), Exit::Jump(Target::Static(entry))) => found.push((block.start, Selector { selector, entry })),
+            (_, Exit::JumpI { .. }) | (_, Exit::FallThrough(_)) => pending.extend(block.successors()),
+            _ => {}, // Reverting, or jumping away to the fallback function
+        }
+    }
+
+    found.sort_by_key(|(start, _)| *start);
+    let mut seen = HashSet::new();
+    found.into_iter().map(|(_, selector)| selector).filter(|selector| seen.insert(selector.selector)).collect()
+}
+
+// CALLDATALOAD PUSH1 0xe0 SHR, keeping the first four bytes of the word read
+fn reads_selector(block: &BasicBlock) -> bool {
+    block.instructions.windows(3).any(|window| {
+        window[0].opcode == OpCode::CallDataLoad as u8
+            && window[1].opcode == OpCode::Push1 as u8 && window[1].immediate == [0xe0]
+            && window[2].opcode == OpCode::Shr as u8
+    })
+}
+
+// The selector compared at the end of the block, if it ends the way a dispatcher's comparison does
+fn comparison(block: &BasicBlock) -> Option<[u8; 4]> {
+    let is = |instruction: &DisassembledInstruction, opcode: OpCode| instruction.opcode == opcode as u8;
+    let is_dup = |instruction: &DisassembledInstruction| (OpCode::Dup1 as u8..=OpCode::Dup16 as u8).contains(&instruction.opcode);
+
+    let mut rest = block.instructions.iter().rev();
+    // The exit has already checked the destination is pushed
+    let (Some(jump), Some(_destination), Some(eq)) = (rest.next(), rest.next(), rest.next()) else {
+        return None;
+    };
+    if !is(jump, OpCode::JumpI) || !is(eq, OpCode::Eq) {
+        return None;
+    }
+    let mut push = rest.next()?;
+    if is_dup(push) {
+        push = rest.next()?;
+    }
+    let size = push.opcode.wrapping_sub(OpCode::Push1 as u8) as usize + 1;
+    if !(1..=4).contains(&size) || push.immediate.len() != size {
+        return None;
+    }
+    let mut selector = [0u8; 4];
+    selector[4 - size..].copy_from_slice(&push.immediate);
+    Some(selector)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::assembler::assemble;
+
+    #[test]
+    fn dispatcher() {
+        // As solc lays it out, with one selector compared in the DUP form and a repeated comparison
+        let code = assemble("
+            PUSH1 0x00 CALLDATALOAD PUSH1 0xe0 SHR
+            DUP1 PUSH4 0x06fdde03 EQ PUSH2 @name JUMPI
+            PUSH4 0xa9059cbb DUP2 EQ PUSH2 @transfer JUMPI
+            DUP1 PUSH4 0x06fdde03 EQ PUSH2 @transfer JUMPI
+            DUP1 PUSH4 0x70a08231 GT PUSH2 @name JUMPI    ; a binary search step, not a function
+            PUSH1 0x00 DUP1 REVERT
+            @name: STOP
+            @transfer: STOP
+        ").unwrap();
+        let selectors = find_selectors(&Disassembly::new(&code));
+        assert_eq!(vec![
+            Selector { selector: [0x06, 0xfd, 0xde, 0x03], entry: 0x36 },
+            Selector { selector: [0xa9, 0x05, 0x9c, 0xbb], entry: 0x38 },
+        ], selectors);
+        assert_eq!(0x5b, code[0x36]);
+        assert_eq!(0x5b, code[0x38]);
+    }
+
+    #[test]
+    fn leading_zeros() {
+        let code = assemble("
+            PUSH0 CALLDATALOAD PUSH1 0xe0 SHR
+            DUP1 PUSH3 0xabcdef EQ PUSH2 @a JUMPI
+            DUP1 PUSH1 0x01 EQ PUSH2 @b JUMPI
+            STOP @a: STOP @b: STOP
+        ").unwrap();
+        assert_eq!(vec![
+            Selector { selector: [0x00, 0xab, 0xcd, 0xef], entry: 0x18 },
+            Selector { selector: [0x00, 0x00, 0x00, 0x01], entry: 0x1a },
+        ], find_selectors(&Disassembly::new(&code)));
+    }
+
+    #[test]
+    fn outside_dispatcher() {
+        // Comparisons without the selector being read from calldata aren't a dispatcher
+        // DUP1 PUSH1 0x01 EQ PUSH2 0x000a JUMPI STOP JUMPDEST STOP
+        assert!(find_selectors(&Disassembly::new(&[0x80, 0x60, 0x01, 0x14, 0x61, 0x00, 0x0a, 0x57, 0x00, 0x5b, 0x00])).is_empty());
+
+        // Nor are those inside a function, or after a binary search step
+        let code = assemble("
+            PUSH0 CALLDATALOAD PUSH1 0xe0 SHR
+            DUP1 PUSH4 0x70a08231 GT PUSH2 @upper JUMPI
+            DUP1 PUSH4 0x06fdde03 EQ PUSH2 @name JUMPI
+            PUSH0 DUP1 REVERT
+            @upper: DUP1 PUSH4 0xa9059cbb EQ PUSH2 @name JUMPI
+            PUSH2 @fallback JUMP
+            @name: DUP1 PUSH1 0x2a EQ PUSH2 @fallback JUMPI STOP
+            @fallback: DUP1 PUSH1 0x07 EQ PUSH2 @name JUMPI STOP
+        ").unwrap();
+        let selectors: Vec<[u8; 4]> = find_selectors(&Disassembly::new(&code)).iter().map(|selector| selector.selector).collect();
+        assert_eq!(vec![[0x06, 0xfd, 0xde, 0x03], [0xa9, 0x05, 0x9c, 0xbb]], selectors);
+    }
+}
@@ -0,0 +1,145 @@
+use super::cfg::{ BasicBlock, ControlFlowGraph };
+use super::disassembler::{ push_value, Disassembly };
+use super::instructions::{ Instructions, OpCode };
+
+// Creation code taken apart. Compilers lay it out as the constructor, then the runtime code it copies into
+// memory and returns, then any ABI encoded constructor arguments appended by whoever deploys it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitCode {
+    pub init_code: Vec<u8>,
+    pub runtime_code: Vec<u8>, // As it appears in the creation code, so with immutables still zeroed
+    pub runtime_offset: usize,
+    pub constructor_arguments: Vec<u8>,
+}
+
+// Finds the CODECOPY that puts the runtime code in memory for RETURN to hand back, and splits the creation
+// code around the region it copies. None if no block copies a constant region of the code and returns.
+//
+// This is done statically, following the constants each block pushes, duplicates and swaps, as constructors
+// can't be relied on to run here without their arguments and state. CODESIZE is a constant too, so the
+// copies solc makes of the arguments, from the end of the runtime code to the end of the code, are known.
+pub fn split(creation_code: &[u8]) -> Option<SplitCode> {
+    let graph = ControlFlowGraph::new(&Disassembly::new(creation_code));
+    let (runtime_offset, size) = graph.blocks.iter().find_map(|block| returned_copy(block, creation_code.len()))?;
+    let end = runtime_offset + size;
+    Some(SplitCode {
+        init_code: creation_code[..runtime_offset].to_vec(),
+        runtime_code: creation_code[runtime_offset..end].to_vec(),
+        runtime_offset,
+        constructor_arguments: creation_code[end..].to_vec(),
+    })
+}
+
+// The offset and size of the last constant CODECOPY in a block that ends by returning
+fn returned_copy(block: &BasicBlock, code_size: usize) -> Option<(usize, usize)> {
+    if block.instructions.last()?.opcode != OpCode::Return as u8 {
+        return None;
+    }
+
+    // Known values on top of the stack, anything beneath them is unknown
+    let mut stack: Vec<Option<usize>> = Vec::new();
+    let pop = |stack: &mut Vec<Option<usize>>| stack.pop().flatten();
+    let mut copy = None;
+    for instruction in &block.instructions {
+        let opcode = instruction.opcode;
+        match opcode {
+            _ if (OpCode::Push0 as u8..=OpCode::Push32 as u8).contains(&opcode) => stack.push(push_value(&instruction.immediate)),
+            _ if (OpCode::Dup1 as u8..=OpCode::Dup16 as u8).contains(&opcode) => {
+                let depth = (opcode - OpCode::Dup1 as u8) as usize + 1;
+                let value = stack.len().checked_sub(depth).and_then(|index| stack[index]);
+                stack.push(value);
+            },
+            _ if (OpCode::Swap1 as u8..=OpCode::Swap16 as u8).contains(&opcode) => {
+                let depth = (opcode - OpCode::Swap1 as u8) as usize + 1;
+                let missing = (depth + 1).saturating_sub(stack.len());
+                stack.splice(0..0, std::iter::repeat_n(None, missing));
+                let top = stack.len() - 1;
+                stack.swap(top, top - depth);
+            },
+            _ if opcode == OpCode::CodeSize as u8 => stack.push(Some(code_size)),
+            _ if opcode == OpCode::Add as u8 || opcode == OpCode::Sub as u8 => {
+                let (a, b) = (pop(&mut stack), pop(&mut stack));
+                let result = a.zip(b).and_then(|(a, b)| if opcode == OpCode::Add as u8 { a.checked_add(b) } else { a.checked_sub(b) });
+                stack.push(result);
+            },
+            _ if opcode == OpCode::CodeCopy as u8 => {
+                let (_destination, offset, size) = (pop(&mut stack), pop(&mut stack), pop(&mut stack));
+                if let (Some(offset), Some(size)) = (offset, size) {
+                    if size > 0 && offset.checked_add(size).is_some_and(|end| end <= code_size) {
+                        copy = Some((offset, size));
+                    }
+                }
+            },
+            _ => {
+                let instruction = Instructions.get(&opcode)?;
+                for _ in 0..instruction.stack_items_removed {
+                    pop(&mut stack);
+                }
+                stack.extend(std::iter::repeat_n(None, instruction.stack_items_added as usize));
+            },
+        }
+    }
+    copy
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::assembler::assemble;
+
+    // PUSH1 0x2a PUSH0 MSTORE PUSH1 0x20 PUSH0 RETURN
+    const RUNTIME: [u8; 8] = [0x60, 0x2a, 0x5f, 0x52, 0x60, 0x20, 0x5f, 0xf3];
+
+    #[test]
+    fn split_code() {
+        // As solc deploys code without immutables, followed by the runtime and an argument
+        let init = assemble("PUSH1 0x08 DUP1 PUSH1 0x0a PUSH0 CODECOPY PUSH0 RETURN INVALID").unwrap();
+        let arguments = [0x01; 32];
+        let code = [&init[..], &RUNTIME, &arguments].concat();
+
+        let split = split(&code).unwrap();
+        assert_eq!(init, split.init_code);
+        assert_eq!(RUNTIME.to_vec(), split.runtime_code);
+        assert_eq!(0x0a, split.runtime_offset);
+        assert_eq!(arguments.to_vec(), split.constructor_arguments);
+    }
+
+    #[test]
+    fn constructor() {
+        // Copying the arguments from the end of the runtime code to the end of the code, jumping to the body
+        // of the constructor, then deploying with an immutable written over the copy
+        let init = assemble("
+            PUSH1 0x80 PUSH1 0x40 MSTORE
+            PUSH1 0x29 CODESIZE SUB DUP1 PUSH1 0x29 PUSH1 0x80 CODECOPY
+            @body JUMP
+            @body:
+            PUSH1 0x08 DUP1 PUSH1 0x21 PUSH0 CODECOPY
+            CALLER PUSH1 0x01 MSTORE
+            PUSH0 RETURN INVALID
+        ").unwrap();
+        assert_eq!(0x21, init.len());
+        let code = [&init[..], &RUNTIME, &[0x02; 32]].concat();
+
+        let split = split(&code).unwrap();
+        assert_eq!(0x21, split.runtime_offset);
+        assert_eq!(RUNTIME.to_vec(), split.runtime_code);
+        assert_eq!(vec![0x02; 32], split.constructor_arguments);
+    }
+
+    #[test]
+    fn no_runtime_code() {
+        // Returning memory that wasn't copied from the code
+        assert_eq!(None, split(&assemble("PUSH1 0x01 PUSH0 RETURN").unwrap()));
+        // Copying more than there is
+        assert_eq!(None, split(&assemble("PUSH1 0x40 DUP1 PUSH0 PUSH0 CODECOPY PUSH0 RETURN").unwrap()));
+        assert_eq!(None, split(&[]));
+    }
+
+    #[test]
+    fn swap_below_known_values() {
+        // The SWAP brings up whatever the block was entered with, so the size copied isn't known
+        let init = assemble("PUSH1 0x08 SWAP1 PUSH1 0x0a PUSH0 CODECOPY PUSH0 RETURN INVALID").unwrap();
+        assert_eq!(None, split(&[&init[..], &RUNTIME].concat()));
+    }
+}
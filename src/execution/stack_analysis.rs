@@ -0,0 +1,176 @@
+use std::cmp;
+use std::collections::{ HashMap, VecDeque };
+use std::fmt;
+
+use super::cfg::{ BasicBlock, ControlFlowGraph };
+use super::instructions::Instructions;
+
+pub const STACK_LIMIT: usize = 1024;
+
+// What a block does to the stack on its own, relative to the height it's entered with
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Effect {
+    required: usize, // Items that must already be there for no instruction to underflow
+    lowest: i64, // Lowest and highest points reached, after each instruction's pops and pushes
+    highest: i64,
+    net: i64,
+}
+
+impl Effect {
+    fn of(block: &BasicBlock) -> Effect {
+        let mut effect = Effect { required: 0, lowest: 0, highest: 0, net: 0 };
+        for instruction in &block.instructions {
+            let Some(instruction) = Instructions.get(&instruction.opcode) else {
+                continue;
+            };
+            let removed = instruction.stack_items_removed as i64;
+            effect.required = cmp::max(effect.required, (removed - effect.net).max(0) as usize);
+            effect.net -= removed;
+            effect.lowest = cmp::min(effect.lowest, effect.net);
+            effect.net += instruction.stack_items_added as i64;
+            effect.highest = cmp::max(effect.highest, effect.net);
+        }
+        effect
+    }
+}
+
+// The stack heights a block can see, from the heights it can be entered with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockStack {
+    pub start: usize,
+    pub required: usize,
+    pub entry: Option<(usize, usize)>, // None if the block is only reached through dynamic jumps, or not at all
+    pub heights: Option<(usize, usize)>,
+    pub underflow: bool, // Guaranteed, whichever way the block is entered
+    pub overflow: bool, // Possible, as how far a loop grows the stack usually depends on the data
+}
+
+// 0012: entry 1-4, height 0-6
+impl fmt::Display for BlockStack {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let range = |(min, max): (usize, usize)| if min == max { format!("{}", min) } else { format!("{}-{}", min, max) };
+        match (self.entry, self.heights) {
+            (Some(entry), Some(heights)) => write!(f, "{:04x}: entry {}, height {}", self.start, range(entry), range(heights))?,
+            _ => write!(f, "{:04x}: entry unknown, needs {}", self.start, self.required)?,
+        }
+        if self.underflow {
+            write!(f, ", underflow: needs {}", self.required)?;
+        }
+        if self.overflow {
+            write!(f, ", overflow: exceeds {}", STACK_LIMIT)?;
+        }
+        Ok(())
+    }
+}
+
+// Stack heights per block, in the graph's order, found without running anything. Entry heights are
+// propagated from the start of the code as ranges, growing until they stop changing. Ranges are capped just
+// past the limit, so a loop that keeps pushing still settles. Nothing is propagated out of a block that
+// always fails.
+pub fn analyse_stack(graph: &ControlFlowGraph) -> Vec<BlockStack> {
+    let effects: HashMap<usize, Effect> = graph.blocks.iter().map(|block| (block.start, Effect::of(block))).collect();
+    let fails = |effect: &Effect, (min, max): (usize, usize)| {
+        max < effect.required || min as i64 + effect.highest > STACK_LIMIT as i64
+    };
+
+    let mut entries: HashMap<usize, (usize, usize)> = HashMap::new();
+    let mut pending = VecDeque::new();
+    if let Some(first) = graph.blocks.first().filter(|block| block.start == 0) {
+        entries.insert(first.start, (0, 0));
+        pending.push_back(first.start);
+    }
+    while let Some(start) = pending.pop_front() {
+        let (block, effect, entry) = (graph.block(start).unwrap(), &effects[&start], entries[&start]);
+        if fails(effect, entry) {
+            continue;
+        }
+        // Entering below the required height fails, so only the heights above it carry on
+        let exit_height = |height: usize| (cmp::max(height, effect.required) as i64 + effect.net) as usize;
+        let exit = (exit_height(entry.0), cmp::min(exit_height(entry.1), STACK_LIMIT + 1));
+        for successor in block.successors() {
+            let joined = match entries.get(&successor) {
+                Some(&(min, max)) => (cmp::min(min, exit.0), cmp::max(max, exit.1)),
+                None => exit,
+            };
+            if entries.get(&successor) != Some(&joined) {
+                entries.insert(successor, joined);
+                pending.push_back(successor);
+            }
+        }
+    }
+
+    graph.blocks.iter().map(|block| {
+        let effect = &effects[&block.start];
+        let entry = entries.get(&block.start).copied();
+        let heights = entry.map(|(min, max)| {
+            ((cmp::max(min, effect.required) as i64 + effect.lowest) as usize, (max as i64 + effect.highest) as usize)
+        });
+        BlockStack {
+            start: block.start,
+            required: effect.required,
+            entry,
+            heights,
+            underflow: entry.is_some_and(|(_, max)| max < effect.required),
+            overflow: heights.is_some_and(|(_, max)| max > STACK_LIMIT),
+        }
+    }).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::assembler::assemble;
+    use crate::execution::disassembler::Disassembly;
+
+    fn analyse(source: &str) -> Vec<BlockStack> {
+        analyse_stack(&ControlFlowGraph::new(&Disassembly::new(&assemble(source).unwrap())))
+    }
+
+    #[test]
+    fn heights() {
+        let blocks = analyse("
+            PUSH1 1 PUSH1 2 CALLVALUE @skip JUMPI   ; entered with 2 or the fall through's 3
+            PUSH1 3
+            @skip: ADD POP STOP
+        ");
+        assert_eq!(Some((0, 0)), blocks[0].entry);
+        assert_eq!(Some((0, 4)), blocks[0].heights);
+        assert_eq!(Some((2, 2)), blocks[1].entry);
+        assert_eq!(Some((2, 3)), blocks[2].entry);
+        assert!(blocks.iter().all(|block| !block.underflow && !block.overflow));
+        assert_eq!("000a: entry 2-3, height 0-3", blocks[2].to_string());
+    }
+
+    #[test]
+    fn underflow() {
+        let blocks = analyse("PUSH1 1 @next JUMP @next: ADD STOP");
+        assert!(!blocks[0].underflow);
+        assert!(blocks[1].underflow);
+        assert_eq!(2, blocks[1].required);
+        assert_eq!("0005: entry 1, height 0-1, underflow: needs 2", blocks[1].to_string());
+
+        // Only one of the ways in underflows, which isn't guaranteed
+        let blocks = analyse("PUSH1 1 CALLVALUE @add JUMPI PUSH1 2 @add: ADD STOP");
+        assert!(!blocks[2].underflow);
+    }
+
+    #[test]
+    fn overflow() {
+        // Pushing forever in a loop
+        let blocks = analyse("@loop: CALLVALUE @loop JUMP");
+        assert!(blocks[0].overflow);
+        assert_eq!(Some((0, STACK_LIMIT + 1)), blocks[0].entry);
+        assert!(!analyse(&"CALLVALUE ".repeat(STACK_LIMIT))[0].overflow);
+        assert!(analyse(&"CALLVALUE ".repeat(STACK_LIMIT + 1))[0].overflow);
+    }
+
+    #[test]
+    fn dynamic_jumps() {
+        // The target's offset is pushed, as a return address would be, but not right before the jump
+        let blocks = analyse("@target PUSH1 0 CALLDATALOAD JUMP @target: STOP");
+        assert_eq!(None, blocks[1].entry);
+        assert!(!blocks[1].underflow);
+        assert_eq!("0006: entry unknown, needs 0", blocks[1].to_string());
+    }
+}
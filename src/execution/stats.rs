@@ -0,0 +1,166 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use super::disassembler::{ Disassembly, Segment };
+use super::host::MAX_CODE_SIZE;
+use super::instructions::{ Instructions, OpCode };
+
+// Opcodes still valid but discouraged: CALLCODE was replaced by DELEGATECALL and SELFDESTRUCT was defanged by
+// EIP-6780.
+const DEPRECATED: [u8; 2] = [OpCode::CallCode as u8, OpCode::SelfDestruct as u8];
+
+// Summary of some code, for comparing what different compilers or settings produce. Data regions, such as
+// metadata, are counted apart from the instructions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Statistics {
+    pub code_size: usize,
+    pub instructions: usize,
+    pub push_bytes: usize, // Immediates, not counting the PUSH opcodes themselves
+    pub data_bytes: usize,
+    pub jump_destinations: usize,
+    pub deprecated: Vec<&'static str>,
+    pub histogram: Vec<(String, usize)>, // Most frequent first, unknown bytes by value
+}
+
+impl Statistics {
+    pub fn new(code: &[u8]) -> Statistics {
+        let disassembly = Disassembly::new(code);
+        let mut counts: HashMap<u8, (String, usize)> = HashMap::new();
+        let mut statistics = Statistics {
+            code_size: code.len(),
+            instructions: 0,
+            push_bytes: 0,
+            data_bytes: 0,
+            jump_destinations: 0,
+            deprecated: Vec::new(),
+            histogram: Vec::new(),
+        };
+        for segment in &disassembly.segments {
+            let instruction = match segment {
+                Segment::Code(instruction) => instruction,
+                Segment::Data(region) => {
+                    statistics.data_bytes += region.bytes.len();
+                    continue;
+                },
+            };
+            statistics.instructions += 1;
+            statistics.push_bytes += instruction.immediate.len();
+            if instruction.opcode == OpCode::JumpDest as u8 {
+                statistics.jump_destinations += 1;
+            }
+            let name = || instruction.mnemonic.map(String::from).unwrap_or_else(|| format!("{:#04x}", instruction.opcode));
+            counts.entry(instruction.opcode).or_insert_with(|| (name(), 0)).1 += 1;
+        }
+
+        for opcode in DEPRECATED {
+            if counts.contains_key(&opcode) {
+                statistics.deprecated.push(Instructions[&opcode].mnemonic);
+            }
+        }
+        let mut histogram: Vec<(u8, (String, usize))> = counts.into_iter().collect();
+        histogram.sort_by_key(|(opcode, (_, count))| (Reverse(*count), *opcode));
+        statistics.histogram = histogram.into_iter().map(|(_, entry)| entry).collect();
+        statistics
+    }
+
+    // Bytes left before EIP-170's limit on deployed code, negative when over it
+    pub fn size_headroom(&self) -> i64 {
+        MAX_CODE_SIZE as i64 - self.code_size as i64
+    }
+
+    pub fn to_text(&self) -> String {
+        let headroom = self.size_headroom();
+        let limit = if headroom < 0 {
+            format!("{} bytes over the EIP-170 limit", -headroom)
+        } else {
+            format!("{} bytes below the EIP-170 limit", headroom)
+        };
+        let deprecated = if self.deprecated.is_empty() { String::from("none") } else { self.deprecated.join(", ") };
+
+        let mut text = format!("Code size: {} bytes ({})\n", self.code_size, limit);
+        text.push_str(&format!("Instructions: {}\n", self.instructions));
+        text.push_str(&format!("Push data: {} bytes\n", self.push_bytes));
+        text.push_str(&format!("Data: {} bytes\n", self.data_bytes));
+        text.push_str(&format!("Jump destinations: {}\n", self.jump_destinations));
+        text.push_str(&format!("Deprecated opcodes: {}\n", deprecated));
+        text.push_str("Opcodes:\n");
+        for (name, count) in &self.histogram {
+            text.push_str(&format!("  {:<14} {}\n", name, count));
+        }
+        text
+    }
+
+    // The histogram is an object keyed by mnemonic, in the same order
+    pub fn to_json(&self) -> String {
+        let deprecated: Vec<String> = self.deprecated.iter().map(|name| format!("\"{}\"", name)).collect();
+        let histogram: Vec<String> = self.histogram.iter().map(|(name, count)| format!("    \"{}\": {}", name, count)).collect();
+        let histogram = if histogram.is_empty() { String::from("{}") } else { format!("{{\n{}\n  }}", histogram.join(",\n")) };
+        format!(
+            "{{\n  \"code_size\": {},\n  \"size_headroom\": {},\n  \"instructions\": {},\n  \"push_bytes\": {},\n  \"data_bytes\": {},\n  \"jump_destinations\": {},\n  \"deprecated\": [{}],\n  \"histogram\": {}\n}}\n",
+            self.code_size, self.size_headroom(), self.instructions, self.push_bytes, self.data_bytes, self.jump_destinations, deprecated.join(", "), histogram,
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::assembler::assemble;
+
+    #[test]
+    fn statistics() {
+        // Pushing @b's offset is enough for it to count as reachable
+        let code = assemble("@a: PUSH2 0x0102 PUSH1 1 ADD PUSH1 2 ADD @b ADD @a JUMP STOP @b: CALLER SELFDESTRUCT").unwrap();
+        let statistics = Statistics::new(&code);
+        assert_eq!(19, statistics.code_size);
+        assert_eq!(MAX_CODE_SIZE as i64 - 19, statistics.size_headroom());
+        assert_eq!(13, statistics.instructions);
+        assert_eq!(5, statistics.push_bytes);
+        assert_eq!(1, statistics.data_bytes); // The unreachable STOP
+        assert_eq!(2, statistics.jump_destinations);
+        assert_eq!(vec!["SELFDESTRUCT"], statistics.deprecated);
+        // Ties in opcode order
+        assert_eq!((String::from("ADD"), 3), statistics.histogram[0]);
+        assert_eq!((String::from("PUSH1"), 3), statistics.histogram[1]);
+        assert_eq!((String::from("JUMPDEST"), 2), statistics.histogram[2]);
+
+        assert_eq!(-1, Statistics::new(&vec![0x5b; MAX_CODE_SIZE + 1]).size_headroom());
+    }
+
+    #[test]
+    fn reports() {
+        let statistics = Statistics::new(&[0x60, 0x01, 0x60, 0x02, 0x01, 0x0c]);
+        let expected = concat!(
+            "Code size: 6 bytes (24570 bytes below the EIP-170 limit)\n",
+            "Instructions: 4\n",
+            "Push data: 2 bytes\n",
+            "Data: 0 bytes\n",
+            "Jump destinations: 0\n",
+            "Deprecated opcodes: none\n",
+            "Opcodes:\n",
+            "  PUSH1          2\n",
+            "  ADD            1\n",
+            "  0x0c           1\n",
+        );
+        assert_eq!(expected, statistics.to_text());
+
+        let expected = concat!(
+            "{\n",
+            "  \"code_size\": 6,\n",
+            "  \"size_headroom\": 24570,\n",
+            "  \"instructions\": 4,\n",
+            "  \"push_bytes\": 2,\n",
+            "  \"data_bytes\": 0,\n",
+            "  \"jump_destinations\": 0,\n",
+            "  \"deprecated\": [],\n",
+            "  \"histogram\": {\n",
+            "    \"PUSH1\": 2,\n",
+            "    \"ADD\": 1,\n",
+            "    \"0x0c\": 1\n",
+            "  }\n",
+            "}\n",
+        );
+        assert_eq!(expected, statistics.to_json());
+    }
+}
@@ -1,8 +1,12 @@
 
-use std::{ cmp, ops };
+use std::{ cmp, hash, ops };
 
+use crate::consensus::rlp;
+use crate::crypto::keccak256;
 
-#[derive(Debug, Copy, Clone)]
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone, Default)]
 pub struct u256 {
     upper: u128,
     lower: u128,
@@ -32,6 +36,106 @@ impl u256 {
     pub fn from_u128s(upper: u128, lower: u128) -> u256 {
         u256 { upper, lower }
     }
+
+    // Big endian, as found in the ROM and memory. Anything over 32 bytes is truncated to the lowest 32.
+    pub fn from_be_bytes(bytes: &[u8]) -> u256 {
+        let mut padded = [0u8; 32];
+        let len = cmp::min(bytes.len(), 32);
+        padded[32 - len..].copy_from_slice(&bytes[bytes.len() - len..]);
+        let mut upper = [0u8; 16];
+        let mut lower = [0u8; 16];
+        upper.copy_from_slice(&padded[..16]);
+        lower.copy_from_slice(&padded[16..]);
+        u256 { upper: u128::from_be_bytes(upper), lower: u128::from_be_bytes(lower) }
+    }
+
+    pub fn to_be_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        bytes[..16].copy_from_slice(&self.upper.to_be_bytes());
+        bytes[16..].copy_from_slice(&self.lower.to_be_bytes());
+        bytes
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.upper == 0 && self.lower == 0
+    }
+
+    // None if the value doesn't fit, e.g. a memory offset that could never be allocated
+    pub fn to_usize(&self) -> Option<usize> {
+        if self.upper != 0 || self.lower > usize::MAX as u128 {
+            return None;
+        }
+        Some(self.lower as usize)
+    }
+
+    pub fn to_u64(&self) -> Option<u64> {
+        if self.upper != 0 || self.lower > u64::MAX as u128 {
+            return None;
+        }
+        Some(self.lower as u64)
+    }
+}
+
+// 160-bit account address. On the stack it is the low 20 bytes of a word.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Address(pub [u8; 20]);
+
+impl Address {
+    pub fn zero() -> Address {
+        Address([0u8; 20])
+    }
+
+    // Upper 12 bytes are discarded
+    pub fn from_u256(value: u256) -> Address {
+        let mut address = [0u8; 20];
+        address.copy_from_slice(&value.to_be_bytes()[12..]);
+        Address(address)
+    }
+
+    pub fn to_u256(&self) -> u256 {
+        u256::from_be_bytes(&self.0)
+    }
+
+    // Rightmost 20 bytes of the hash
+    fn from_hash(hash: [u8; 32]) -> Address {
+        let mut address = [0u8; 20];
+        address.copy_from_slice(&hash[12..]);
+        Address(address)
+    }
+
+    // Address of an account created by CREATE or a creation transaction, KEC(RLP((sender, nonce)))
+    pub fn from_create(sender: &Address, nonce: u64) -> Address {
+        let encoded = rlp::encode_list(&[rlp::encode_bytes(&sender.0), rlp::encode_u64(nonce)]);
+        Address::from_hash(keccak256(&encoded))
+    }
+
+    // EIP-1014 address of an account created by CREATE2, KEC(0xff ++ sender ++ salt ++ KEC(init))
+    pub fn from_create2(sender: &Address, salt: &u256, init_code: &[u8]) -> Address {
+        let mut preimage = Vec::with_capacity(85);
+        preimage.push(0xff);
+        preimage.extend_from_slice(&sender.0);
+        preimage.extend_from_slice(&salt.to_be_bytes());
+        preimage.extend_from_slice(&keccak256(init_code));
+        Address::from_hash(keccak256(&preimage))
+    }
+}
+
+// 256-bit hash, e.g. a Keccak-256 digest
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct H256(pub [u8; 32]);
+
+impl H256 {
+    pub fn zero() -> H256 {
+        H256([0u8; 32])
+    }
+
+    pub fn from_u256(value: u256) -> H256 {
+        H256(value.to_be_bytes())
+    }
+
+    pub fn to_u256(&self) -> u256 {
+        u256::from_be_bytes(&self.0)
+    }
 }
 
 // Arithmetic
@@ -44,7 +148,7 @@ impl ops::Add for u256 {
         if overflow {
             intermediate_upper = u128::overflowing_add(intermediate_upper, 1).0;
         }
-        let (upper, overflow) = u128::overflowing_add(self.upper, intermediate_upper);
+        let (upper, _overflow) = u128::overflowing_add(self.upper, intermediate_upper);
         u256::from_u128s(upper, lower)
     }
 }
@@ -97,32 +201,40 @@ impl cmp::PartialEq<u256> for u256 {
     }
 }
 
+impl cmp::Eq for u256 {}
+
+// Needed to key storage maps, must agree with eq
+impl hash::Hash for u256 {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.upper.hash(state);
+        self.lower.hash(state);
+    }
+}
+
 // lt, le, gt, ge
 impl u256 {
     fn less_than(&self, other: &Self, equal: bool) -> bool {
         if self.upper < other.upper {
-            return true;
+            true
         } else if self.upper > other.upper {
-            return false;
+            false
+        } else if self.lower < other.lower {
+            true
+        } else if self.lower > other.lower {
+            false
         } else {
-            if self.lower < other.lower {
-                return true;
-            } else if self.lower > other.lower {
-                return false;
-            } else {
-                return equal;
-            }
+            equal
         }
     }
 }
 impl cmp::PartialOrd for u256 {
     fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
         if self.lt(other) {
-            return Some(cmp::Ordering::Less);
+            Some(cmp::Ordering::Less)
         } else if self.gt(other) {
-            return Some(cmp::Ordering::Greater);
+            Some(cmp::Ordering::Greater)
         } else {
-            return Some(cmp::Ordering::Equal);
+            Some(cmp::Ordering::Equal)
         }
     }
 
@@ -216,4 +328,49 @@ mod tests {
         let res: u256 = u256::zero();
         assert_eq!(res, var1 % var2);
     }
+
+    #[test]
+    fn address() {
+        let var1: u256 = u256::max();
+        let res: u256 = u256::from_u128s((1 << 32) - 1, u128::MAX);
+        assert_eq!(res, Address::from_u256(var1).to_u256());
+    }
+
+    fn address_from_hex(s: &str) -> Address {
+        let mut address = [0u8; 20];
+        for (i, byte) in address.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&s[2 * i..2 * i + 2], 16).unwrap();
+        }
+        Address(address)
+    }
+
+    #[test]
+    fn create_address() {
+        let sender = address_from_hex("6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0");
+        assert_eq!(address_from_hex("cd234a471b72ba2f1ccf0a70fcaba648a5eecd8d"), Address::from_create(&sender, 0));
+        assert_eq!(address_from_hex("343c43a37d37dff08ae8c4a11544c718abb4fcf8"), Address::from_create(&sender, 1));
+        assert_eq!(address_from_hex("f778b86fa74e846c4f0a1fbd1335fe81c00a0c91"), Address::from_create(&sender, 2));
+    }
+
+    #[test]
+    fn create2_address() {
+        // Examples from EIP-1014
+        let deadbeef = address_from_hex("deadbeef00000000000000000000000000000000");
+        assert_eq!(address_from_hex("4d1a2e2bb4f88f0250f26ffff098b0b30b26bf38"), Address::from_create2(&Address::zero(), &u256::zero(), &[0x00]));
+        assert_eq!(address_from_hex("b928f69bb1d91cd65274e3c79d8986362984fda3"), Address::from_create2(&deadbeef, &u256::zero(), &[0x00]));
+        let salt = u256::from_u128s(0x000000000000000000000000feed0000, 0);
+        assert_eq!(address_from_hex("d04116cdd17bebe565eb2422f2497e06cc1c9833"), Address::from_create2(&deadbeef, &salt, &[0x00]));
+        assert_eq!(address_from_hex("70f2b2914a2a4b783faefb75f459a580616fcb5e"), Address::from_create2(&Address::zero(), &u256::zero(), &[0xde, 0xad, 0xbe, 0xef]));
+    }
+
+    #[test]
+    fn be_bytes() {
+        let var1: u256 = u256::from_be_bytes(&[0x12, 0x34]);
+        assert_eq!(u256::from_u128s(0, 0x1234), var1);
+        assert_eq!([0x12, 0x34], var1.to_be_bytes()[30..]);
+
+        let bytes: [u8; 32] = [0xff; 32];
+        assert_eq!(u256::max(), u256::from_be_bytes(&bytes));
+        assert_eq!(bytes, u256::max().to_be_bytes());
+    }
 }
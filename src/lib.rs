@@ -0,0 +1,6 @@
+#[macro_use]
+extern crate lazy_static;
+
+pub mod consensus;
+pub mod crypto;
+pub mod execution;
@@ -1,16 +1,21 @@
-
-#[macro_use]
-extern crate lazy_static;
-
-use std::path::PathBuf;
 use std::fs::File;
-use std::io::Read;
+use std::io::{ self, Read };
+use std::path::{ Path, PathBuf };
+use std::process;
 
-mod execution;
-use crate::execution::instructions::{ Instructions };
-use crate::execution::program_context::{ ProgramContext, Rom, ROMOutOfBoundsError };
+use ethereum::execution::assembler;
+use ethereum::execution::cfg::ControlFlowGraph;
+use ethereum::execution::diff::Diff;
+use ethereum::execution::disassembler::{ self, Disassembly };
+use ethereum::execution::environment::Environment;
+use ethereum::execution::host::InMemoryHost;
+use ethereum::execution::program_context::{ encode_hex, LoadError, ProgramContext, Rom };
+use ethereum::execution::selectors;
+use ethereum::execution::split;
+use ethereum::execution::stack_analysis;
+use ethereum::execution::stats::Statistics;
 
-use clap::{ Parser, Subcommand };
+use clap::{ ArgEnum, Parser, Subcommand };
 
 #[derive(Parser)]
 struct Args {
@@ -20,72 +25,299 @@ struct Args {
 
 #[derive(Subcommand)]
 enum Commands {
-    Disassemble {
+    Assemble {
+        /// File to read mnemonic source from, or - for stdin
         #[clap(short, long, parse(from_os_str))]
         filename: PathBuf,
     },
+    Cfg {
+        #[clap(flatten)]
+        input: Input,
+    },
+    Diff {
+        /// Files to compare, either of which can be - for stdin
+        #[clap(parse(from_os_str))]
+        old: PathBuf,
+        #[clap(parse(from_os_str))]
+        new: PathBuf,
+
+        /// Encoding of the files, auto treats them as hex if they only contain hex digits and whitespace
+        #[clap(long, arg_enum, default_value = "auto")]
+        format: Format,
+
+        /// Ignore changed PUSH32 values, as immutables are embedded that way
+        #[clap(long)]
+        ignore_immutables: bool,
+    },
+    Disassemble {
+        #[clap(flatten)]
+        input: Input,
+
+        #[clap(long, arg_enum, default_value = "text")]
+        output: Output,
+    },
     Run {
-        #[clap(short, long, parse(from_os_str))]
-        filename: PathBuf,
+        #[clap(flatten)]
+        input: Input,
+    },
+    Selectors {
+        #[clap(flatten)]
+        input: Input,
+    },
+    Split {
+        #[clap(flatten)]
+        input: Input,
+    },
+    Stack {
+        #[clap(flatten)]
+        input: Input,
     },
+    Stats {
+        #[clap(flatten)]
+        input: Input,
+
+        #[clap(long, arg_enum, default_value = "text")]
+        output: Report,
+    },
+}
+
+// Where the bytecode comes from, shared by every subcommand
+#[derive(clap::Args)]
+struct Input {
+    /// File to read the bytecode from, or - for stdin
+    #[clap(short, long, parse(from_os_str), required_unless_present = "code", conflicts_with = "code")]
+    filename: Option<PathBuf>,
+
+    /// Hex bytecode given inline, e.g. 0x6001
+    #[clap(short, long)]
+    code: Option<String>,
+
+    /// Encoding of the file, auto treats it as hex if it only contains hex digits and whitespace
+    #[clap(long, arg_enum, default_value = "auto", conflicts_with = "code")]
+    format: Format,
+}
+
+// How disassembly is printed
+#[derive(Clone, Copy, ArgEnum)]
+enum Output {
+    Text,
+    Json,
+    Evmasm,
+}
+
+// How analysis reports are printed
+#[derive(Clone, Copy, ArgEnum)]
+enum Report {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, ArgEnum)]
+enum Format {
+    Hex,
+    Bin,
+    Auto,
+}
+
+impl Input {
+    fn name(&self) -> String {
+        match &self.filename {
+            Some(filename) if filename.as_os_str() == "-" => String::from("stdin"),
+            Some(filename) => filename.display().to_string(),
+            None => String::from("inline code"),
+        }
+    }
+
+    fn load(&self) -> Result<Rom, LoadError> {
+        match &self.filename {
+            Some(filename) => load_file(filename, self.format),
+            None => Rom::from_hex(self.code.as_deref().unwrap_or_default()),
+        }
+    }
+}
+
+fn load_file(filename: &Path, format: Format) -> Result<Rom, LoadError> {
+    let contents = read_file(filename)?;
+    match format {
+        Format::Hex => Rom::from_hex(&String::from_utf8_lossy(&contents)),
+        Format::Bin => Ok(Rom::new(contents)),
+        Format::Auto if is_hex(&contents) => Rom::from_hex(&String::from_utf8_lossy(&contents)),
+        Format::Auto => Ok(Rom::new(contents)),
+    }
 }
 
-fn run(filename: &PathBuf) {
+// The whole of the file, or of stdin for -
+fn read_file(filename: &Path) -> io::Result<Vec<u8>> {
+    let mut contents = Vec::new();
+    if filename.as_os_str() == "-" {
+        io::stdin().read_to_end(&mut contents)?;
+    } else {
+        File::open(filename)?.read_to_end(&mut contents)?;
+    }
+    Ok(contents)
+}
 
-    let mut rom = load_rom_from_file(filename);
-    let mut program_context: ProgramContext = ProgramContext::new(rom);
+// Hex digits and whitespace, with an optional 0x prefix. Raw bytecode essentially never looks like this.
+fn is_hex(contents: &[u8]) -> bool {
+    let text = contents.trim_ascii_start();
+    let digits = text.strip_prefix(b"0x").or_else(|| text.strip_prefix(b"0X")).unwrap_or(text);
+    !text.is_empty() && digits.iter().all(|byte| byte.is_ascii_hexdigit() || byte.is_ascii_whitespace())
+}
 
-    loop {
-        match program_context.rom.next_byte() {
+// Exits with an error if the bytecode can't be loaded, as no command can do anything without it
+fn load(input: &Input) -> Rom {
+    match input.load() {
+        Err(err) => {
+            eprintln!("Failed to load {}: {}", input.name(), err);
+            process::exit(1)
+        },
+        Ok(rom) => rom,
+    }
+}
+
+fn run(input: &Input) {
+    let rom = load(input);
+    let mut host = InMemoryHost::new();
+    let mut program_context: ProgramContext = ProgramContext::new(rom, Environment::default(), &mut host);
+
+    match program_context.run() {
+        Err(err) => println!("{}", err),
+        Ok(()) => println!("Execution finished"),
+    }
+    if !program_context.output.is_empty() {
+        println!("Output: 0x{}", encode_hex(&program_context.output));
+    }
+    for log in &program_context.logs {
+        let topics: Vec<String> = log.topics.iter().map(|topic| format!("0x{}", encode_hex(&topic.0))).collect();
+        println!("Log: address 0x{} topics [{}] data 0x{}", encode_hex(&log.address.0), topics.join(", "), encode_hex(&log.data));
+    }
+}
+
+fn assemble(filename: &Path) {
+    let source = match read_file(filename) {
+        Err(err) => {
+            eprintln!("Failed to load {}: {}", filename.display(), err);
+            process::exit(1)
+        },
+        Ok(source) => source,
+    };
+
+    match assembler::assemble(&String::from_utf8_lossy(&source)) {
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1)
+        },
+        Ok(code) => println!("{}", encode_hex(&code)),
+    }
+}
+
+fn cfg(input: &Input) {
+    let rom = load(input);
+    print!("{}", ControlFlowGraph::new(&Disassembly::new(rom.code())).to_dot());
+}
+
+fn diff(old: &Path, new: &Path, format: Format, ignore_immutables: bool) {
+    let mut roms = Vec::new();
+    for filename in [old, new] {
+        match load_file(filename, format) {
             Err(err) => {
-                println!("{}", err);
-                break
+                eprintln!("Failed to load {}: {}", filename.display(), err);
+                process::exit(1)
             },
-            Ok(opcode) => {
-                match Instructions.get(&opcode) {
-                    Some(instruction) => {
-                        println!("{}: {:?}", opcode, instruction);
-                        instruction.execute(&mut program_context);
-                    },
-                    None => println!("This should raise an exception. OpCode missing {}", opcode)
-                }
-            }
+            Ok(rom) => roms.push(rom),
         }
     }
+    let diff = Diff::new(roms[0].code(), roms[1].code(), ignore_immutables);
+    if diff.is_empty() {
+        println!("No differences");
+    } else {
+        print!("{}", diff.to_text());
+    }
 }
 
-fn load_rom_from_file(filename: &PathBuf) -> Rom {
-    let mut file = match File::open(&filename.as_path()) {
-        Err(err) => panic!("Failed to open file: {}, {}", filename.as_path().display(), err),
-        Ok(file) => file,
-    };
+fn disassemble(input: &Input, output: Output) {
+    let rom = load(input);
+    let disassembly = Disassembly::new(rom.code());
+    match output {
+        Output::Text => print!("{}", disassembler::to_text(&disassembly)),
+        Output::Json => print!("{}", disassembler::to_json(&disassembly)),
+        Output::Evmasm => print!("{}", disassembler::to_evmasm(&disassembly)),
+    }
+}
 
-    let mut contents = String::new();
-    match file.read_to_string(&mut contents) {
-        Err(err) => panic!("Failed to read file: {}, {}", filename.as_path().display(), err),
-        Ok(_) => println!("Contents: {}", contents),
+fn list_selectors(input: &Input) {
+    let rom = load(input);
+    for selector in selectors::find_selectors(&Disassembly::new(rom.code())) {
+        println!("0x{} {:04x}", encode_hex(&selector.selector), selector.entry);
     }
+}
 
-    Rom::from_string(&contents)
+fn split_code(input: &Input) {
+    let rom = load(input);
+    let split = match split::split(rom.code()) {
+        Some(split) => split,
+        None => {
+            println!("No runtime code found, {} doesn't copy part of itself and return it", input.name());
+            return;
+        },
+    };
+    println!("Init code: 0x{}", encode_hex(&split.init_code));
+    println!("Runtime code (at {:04x}): 0x{}", split.runtime_offset, encode_hex(&split.runtime_code));
+    if split.constructor_arguments.is_empty() {
+        println!("Constructor arguments: none");
+    } else {
+        println!("Constructor arguments: 0x{}", encode_hex(&split.constructor_arguments));
+    }
 }
 
-fn disassemble(filename: &PathBuf) {
-    println!("Decompiling {}", filename.as_path().display());
-    let mut prog = load_rom_from_file(filename);
-    match prog.disassemble() {
-        Err(err) => println!("{}", err),
-        Ok(_) => {}
+fn analyse_stack(input: &Input) {
+    let rom = load(input);
+    let blocks = stack_analysis::analyse_stack(&ControlFlowGraph::new(&Disassembly::new(rom.code())));
+    for block in &blocks {
+        println!("{}", block);
+    }
+    let problems = blocks.iter().filter(|block| block.underflow || block.overflow).count();
+    println!("{} of {} blocks can fail on the stack", problems, blocks.len());
+}
+
+fn stats(input: &Input, output: Report) {
+    let rom = load(input);
+    let statistics = Statistics::new(rom.code());
+    match output {
+        Report::Text => print!("{}", statistics.to_text()),
+        Report::Json => print!("{}", statistics.to_json()),
     }
 }
 
 fn main() {
     let args = Args::parse();
     match &args.command {
-        Commands::Disassemble { filename } => {
-            disassemble(filename);
+        Commands::Assemble { filename } => {
+            assemble(filename);
+        },
+        Commands::Cfg { input } => {
+            cfg(input);
+        },
+        Commands::Diff { old, new, format, ignore_immutables } => {
+            diff(old, new, *format, *ignore_immutables);
+        },
+        Commands::Disassemble { input, output } => {
+            disassemble(input, *output);
+        },
+        Commands::Run { input } => {
+            run(input);
+        },
+        Commands::Selectors { input } => {
+            list_selectors(input);
+        },
+        Commands::Split { input } => {
+            split_code(input);
+        },
+        Commands::Stack { input } => {
+            analyse_stack(input);
         },
-        Commands::Run { filename } => {
-            run(filename);
+        Commands::Stats { input, output } => {
+            stats(input, *output);
         }
     }
 }